@@ -0,0 +1,167 @@
+use axum::{
+    response::{IntoResponse, Response},
+    extract::{Json, State},
+    http::{header, StatusCode},
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::models::{AppState, SnapshotBatchRequest};
+use crate::services::{redact_text, CapturedFrame, SnapshotFormat};
+use crate::utils::send_feishu_notification;
+
+/// 多帧截图的返回方式
+enum BatchOutput {
+    Zip,
+    Multipart,
+    Json,
+}
+
+impl BatchOutput {
+    fn parse(name: Option<&str>) -> Result<Self, String> {
+        match name.map(|s| s.to_ascii_lowercase()).as_deref() {
+            None | Some("zip") => Ok(Self::Zip),
+            Some("multipart") => Ok(Self::Multipart),
+            Some("json") => Ok(Self::Json),
+            Some(other) => Err(format!("不支持的返回方式: {}，可选 zip/multipart/json", other)),
+        }
+    }
+}
+
+// 文件名带上序号和时间戳，解压后按名称排序即为时间顺序
+fn frame_filename(index: usize, frame: &CapturedFrame, format: SnapshotFormat) -> String {
+    format!("frame_{:03}_{:.3}s.{}", index + 1, frame.timestamp, format.extension())
+}
+
+// 图片已经压缩过，ZIP中直接存储不再压缩
+fn build_zip(frames: &[CapturedFrame], format: SnapshotFormat) -> Result<Vec<u8>, String> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored);
+
+    for (i, frame) in frames.iter().enumerate() {
+        zip.start_file(frame_filename(i, frame, format), options)
+            .map_err(|e| format!("写入ZIP失败: {}", e))?;
+        zip.write_all(&frame.data)
+            .map_err(|e| format!("写入ZIP失败: {}", e))?;
+    }
+
+    let cursor = zip.finish().map_err(|e| format!("写入ZIP失败: {}", e))?;
+    Ok(cursor.into_inner())
+}
+
+// multipart/mixed，每帧一个part，时间戳放在 X-Timestamp 头中
+fn build_multipart(frames: &[CapturedFrame], format: SnapshotFormat, boundary: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    for (i, frame) in frames.iter().enumerate() {
+        let part_header = format!(
+            "--{}\r\nContent-Type: {}\r\nContent-Disposition: attachment; filename=\"{}\"\r\nX-Timestamp: {}\r\nContent-Length: {}\r\n\r\n",
+            boundary,
+            format.content_type(),
+            frame_filename(i, frame, format),
+            frame.timestamp,
+            frame.data.len()
+        );
+        body.extend_from_slice(part_header.as_bytes());
+        body.extend_from_slice(&frame.data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    body
+}
+
+// 多帧截图接口
+//
+// 只打开一次视频流，按时间戳列表或固定间隔截取多帧
+pub async fn take_snapshot_batch(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SnapshotBatchRequest>
+) -> Response {
+    let prepared = payload.to_timestamps().and_then(|timestamps| {
+        let mut options = payload.image.to_options()?;
+        state.video_service.validate_overlay(options.overlay.as_ref())?;
+        let output = BatchOutput::parse(payload.output.as_deref())?;
+        let input = state.sources.resolve(payload.source_id.as_deref(), payload.url.as_deref())?;
+        // 视频源登记的遮挡总会生效
+        options.masks.extend(input.masks.iter().cloned());
+        Ok((timestamps, options, output, input))
+    });
+    let (timestamps, options, output, input) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    tracing::info!("Received batch snapshot request for: {}, {} frames", input.label, timestamps.len());
+
+    let profile = payload.profile.as_deref().or(input.profile.as_deref());
+
+    let frames = match state.video_service.capture_frames(&input.url, &timestamps, profile, &options).await {
+        Ok(frames) => frames,
+        Err(e) => {
+            let e = redact_text(&e);
+            tracing::error!("Failed to capture frames: {}", e);
+
+            // 飞书通知：多帧截图失败
+            let msg = format!(
+                "【多帧截图失败】\n视频源: {}\n帧数: {}\n错误: {}",
+                input.label, timestamps.len(), e
+            );
+            send_feishu_notification(&state, &msg).await;
+
+            let err = serde_json::json!({"error": format!("多帧截图失败: {}", e)});
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response();
+        }
+    };
+
+    // 飞书通知：多帧截图成功
+    let msg = format!(
+        "【多帧截图成功】\n视频源: {}\n帧数: {}/{}",
+        input.label, frames.len(), timestamps.len()
+    );
+    send_feishu_notification(&state, &msg).await;
+
+    let format = options.format;
+    match output {
+        BatchOutput::Zip => match build_zip(&frames, format) {
+            Ok(data) => (
+                [
+                    (header::CONTENT_TYPE, "application/zip".to_string()),
+                    (header::CONTENT_DISPOSITION, "attachment; filename=\"snapshots.zip\"".to_string()),
+                ],
+                data
+            ).into_response(),
+            Err(e) => {
+                let err = serde_json::json!({"error": e});
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+            }
+        },
+        BatchOutput::Multipart => {
+            let boundary = format!("frame-{}", uuid::Uuid::new_v4().simple());
+            let body = build_multipart(&frames, format, &boundary);
+            (
+                [(header::CONTENT_TYPE, format!("multipart/mixed; boundary={}", boundary))],
+                body
+            ).into_response()
+        }
+        BatchOutput::Json => {
+            let frames: Vec<_> = frames
+                .iter()
+                .map(|frame| serde_json::json!({
+                    "timestamp": frame.timestamp,
+                    "data": BASE64.encode(&frame.data),
+                }))
+                .collect();
+            let body = serde_json::json!({
+                "content_type": format.content_type(),
+                "frames": frames,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use axum::{
+    response::{IntoResponse, Response},
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use std::sync::Arc;
+
+use crate::models::{AppState, PinClipRequest};
+
+// 检查clips目录下的剪辑是否存在，打包输出使用子目录名
+fn check_clip(state: &AppState, name: &str) -> Result<(), (StatusCode, String)> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err((StatusCode::BAD_REQUEST, format!("无效的文件名: {}", name)));
+    }
+    if !std::path::Path::new(&state.video_service.clip_path(name)).exists() {
+        return Err((StatusCode::NOT_FOUND, format!("剪辑文件不存在: {}", name)));
+    }
+    Ok(())
+}
+
+// 查询剪辑的元数据（来源视频源、固定状态）
+pub async fn get_clip(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>
+) -> Response {
+    if let Err((status, e)) = check_clip(&state, &name) {
+        let err = serde_json::json!({"error": e});
+        return (status, Json(err)).into_response();
+    }
+    match state.clips.get(&name) {
+        Some(clip) => (StatusCode::OK, Json(clip)).into_response(),
+        None => (StatusCode::OK, Json(serde_json::json!({"name": name}))).into_response(),
+    }
+}
+
+// 固定剪辑，固定的剪辑不会被保留策略清理，可以指定保全到期时间
+pub async fn pin_clip(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    payload: Option<Json<PinClipRequest>>
+) -> Response {
+    if let Err((status, e)) = check_clip(&state, &name) {
+        let err = serde_json::json!({"error": e});
+        return (status, Json(err)).into_response();
+    }
+    let Json(payload) = payload.unwrap_or_default();
+    let until = match payload.until() {
+        Ok(until) => until,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+
+    match state.clips.pin(&name, until, payload.reason) {
+        Ok(clip) => (StatusCode::OK, Json(clip)).into_response(),
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+        }
+    }
+}
+
+// 取消固定，剪辑重新按保留策略清理
+pub async fn unpin_clip(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>
+) -> Response {
+    if let Err((status, e)) = check_clip(&state, &name) {
+        let err = serde_json::json!({"error": e});
+        return (status, Json(err)).into_response();
+    }
+    match state.clips.unpin(&name) {
+        Ok(Some(clip)) => (StatusCode::OK, Json(clip)).into_response(),
+        Ok(None) => (StatusCode::OK, Json(serde_json::json!({"name": name}))).into_response(),
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+        }
+    }
+}
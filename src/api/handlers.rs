@@ -9,10 +9,12 @@ use sysinfo::System;
 use serde_json;
 
 use crate::models::{
-    AppState, SnapshotRequest, ClipRequest, 
-    ConcurrentStats, SystemStats
+    AppState, SnapshotRequest, ClipRequest, AudioRequest, ConcatRequest, PreviewRequest, ProbeRequest, SpriteRequest,
+    AudioResponse, ClipResponse, ConcurrentStats, PreviewResponse, SpriteResponse, SystemStats
 };
-use crate::utils::{create_error_image, send_feishu_notification};
+use crate::services::{redact_text, ConcatSegment, JobCancelGuard, JobStatus, OutputContainer, StreamInput};
+use crate::utils::{create_error_image, public_url, send_feishu_notification, ErrorCategory, ErrorFormat};
+use crate::api::jobs::prepare_clip_params;
 
 // 获取当前并发请求数量的API接口
 pub async fn get_concurrent_requests(State(state): State<Arc<AppState>>) -> impl IntoResponse {
@@ -69,77 +71,223 @@ pub async fn take_snapshot(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<SnapshotRequest>
 ) -> Response {
-    tracing::info!("Received snapshot request for URL: {}", payload.url);
+    let input = match state.sources.resolve(payload.source_id.as_deref(), payload.url.as_deref()) {
+        Ok(input) => input,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    let options = match payload.image.to_options().and_then(|mut options| {
+        state.video_service.validate_overlay(options.overlay.as_ref())?;
+        // 视频源登记的遮挡总会生效，请求中的遮挡在此基础上追加
+        options.masks.extend(input.masks.iter().cloned());
+        Ok(options)
+    }) {
+        Ok(options) => options,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    let on_error = match payload.on_error.as_deref().map(ErrorFormat::parse).transpose() {
+        Ok(on_error) => on_error.unwrap_or_default(),
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    tracing::info!("Received snapshot request for: {}", input.label);
     
     // 默认在视频开始处截图，如果指定了时间戳则使用指定时间
     let timestamp = payload.timestamp.unwrap_or(0.0);
     tracing::info!("Taking snapshot at timestamp: {} seconds", timestamp);
     
-    match state.video_service.capture_frame(&payload.url, timestamp).await {
+    // 请求指定的摄像头配置优先于视频源登记的配置
+    let profile = payload.profile.as_deref().or(input.profile.as_deref());
+    
+    match state.video_service.capture_frame(&input.url, timestamp, profile, &options).await {
         Ok(image_data) => {
             tracing::info!("Successfully captured frame, size: {} bytes", image_data.len());
             
             // 飞书通知：截图成功
             let msg = format!(
-                "【截图成功】\nURL: {}\n时间戳: {} 秒\n图片大小: {} 字节",
-                payload.url, timestamp, image_data.len()
+                "【截图成功】\n视频源: {}\n时间戳: {} 秒\n图片大小: {} 字节",
+                input.label, timestamp, image_data.len()
             );
             send_feishu_notification(&state, &msg).await;
             
             (
-                [("Content-Type", "image/png")],
+                [("Content-Type", options.format.content_type())],
                 image_data
             ).into_response()
         }
         Err(e) => {
+            // 错误图片和JSON错误返回相同的内容，都去掉地址中的凭据
+            let e = redact_text(&e);
             tracing::error!("Failed to capture frame: {}", e);
             
             // 飞书通知：截图失败
             let msg = format!(
-                "【截图失败】\nURL: {}\n时间戳: {} 秒\n错误: {}",
-                payload.url, timestamp, e
+                "【截图失败】\n视频源: {}\n时间戳: {} 秒\n错误: {}",
+                input.label, timestamp, e
             );
             send_feishu_notification(&state, &msg).await;
             
-            // 返回错误图片或错误信息
-            let error_img = create_error_image(&format!("Error: {}", e));
-            (
-                [("Content-Type", "image/png")],
-                error_img
-            ).into_response()
+            // 按请求返回错误图片或错误信息
+            let category = ErrorCategory::classify(&e);
+            match on_error {
+                ErrorFormat::Image => {
+                    let error_img = create_error_image(&e, &input.label, category);
+                    (
+                        [("Content-Type", "image/png"), ("X-Error-Category", category.name())],
+                        error_img
+                    ).into_response()
+                }
+                ErrorFormat::Json => {
+                    let err = serde_json::json!({"error": e, "category": category, "source": input.label});
+                    (category.status(), Json(err)).into_response()
+                }
+            }
         }
     }
 }
 
 // 视频流截取接口
+//
+// 与异步任务共用同一个任务队列，这里提交任务后等待其结束再返回
 pub async fn clip_video(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<ClipRequest>
 ) -> impl IntoResponse {
-    let start = payload.start.unwrap_or(0.0);
-    let duration = payload.duration;
     let return_url = payload.return_url.unwrap_or(true); // 默认true
     
-    match state.video_service.clip_video(&payload.url, start, duration).await {
+    let params = match prepare_clip_params(&state, &payload) {
+        Ok(params) if params.output.package.is_some() && !return_url => {
+            let err = serde_json::json!({"error": "打包输出只能返回地址，return_url 不能为 false"});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+        Ok(params) => params,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    
+    let job = match state.job_queue.submit(params) {
+        Ok(job) => job,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(err)).into_response();
+        }
+    };
+    
+    // 客户端断开时handler被丢弃，守卫会取消任务
+    let mut cancel_guard = JobCancelGuard::new(&state.job_queue, &job.id);
+    let job = state.job_queue.wait(&job.id).await;
+    cancel_guard.disarm();
+    
+    let Some(job) = job else {
+        let err = serde_json::json!({"error": "视频截取任务丢失"});
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response();
+    };
+    
+    match (job.status, job.filename, job.video_url) {
+        (JobStatus::Succeeded, Some(filename), Some(video_url)) => {
+            if return_url {
+                // 返回地址
+                (StatusCode::OK, Json(ClipResponse { video_url })).into_response()
+            } else {
+                // 直接返回视频流
+                let video_path = state.video_service.clip_path(&filename);
+                let content_type = OutputContainer::from_path(&filename)
+                    .unwrap_or_default()
+                    .content_type();
+                match tokio::fs::read(&video_path).await {
+                    Ok(data) => (
+                        [("Content-Type", content_type)],
+                        data
+                    ).into_response(),
+                    Err(e) => (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("读取视频文件失败: {}", e)
+                    ).into_response(),
+                }
+            }
+        }
+        _ => {
+            let e = job.error.unwrap_or_default();
+            let err = serde_json::json!({"error": format!("视频截取失败: {}", e)});
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+        }
+    }
+}
+
+// 多段拼接接口
+//
+// 失败时响应中的 segment 为失败段在 segments 中的下标
+pub async fn concat_clips(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ConcatRequest>
+) -> impl IntoResponse {
+    let return_url = payload.return_url.unwrap_or(true);
+    
+    let options = match payload.to_options() {
+        Ok(options) => options,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    
+    let mut segments = Vec::new();
+    let mut labels = Vec::new();
+    for (i, segment) in payload.segments.iter().enumerate() {
+        let resolved = segment.validate().and_then(|_| {
+            let input = state.sources.resolve(segment.source_id.as_deref(), segment.url.as_deref())?;
+            let profile = segment.profile.clone().or(input.profile.clone());
+            state.video_service.validate_profile(profile.as_deref())?;
+            Ok((input, profile))
+        });
+        match resolved {
+            Ok((input, profile)) => {
+                labels.push(input.label);
+                segments.push(ConcatSegment {
+                    url: input.url,
+                    start: segment.start.unwrap_or(0.0),
+                    duration: segment.duration,
+                    profile,
+                    masks: input.masks,
+                });
+            }
+            Err(e) => {
+                let err = serde_json::json!({"error": format!("第 {} 段: {}", i + 1, e), "segment": i});
+                return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+            }
+        }
+    }
+    tracing::info!("Received concat request with {} segments", segments.len());
+    
+    match state.video_service.concat_clips(&segments, &options).await {
         Ok(filename) => {
-            let video_path = format!("clips/{}", filename);
+            // 多段来自不同视频源时按第一个视频源的策略清理
+            let source_id = payload.segments.iter().find_map(|segment| segment.source_id.as_deref());
+            record_source(&state, &filename, source_id);
+            let video_url = format!("/clips/{}", filename);
             
-            // 飞书通知：截视频成功
+            // 飞书通知：拼接成功
             let msg = format!(
-                "【视频截取成功】\nURL: {}\n起始: {} 秒\n时长: {} 秒\n文件: {}",
-                payload.url, start, duration, filename
+                "【视频拼接成功】\n视频源: {}\n段数: {}\n文件: {}",
+                labels.join(", "), segments.len(), public_url(&video_url)
             );
             send_feishu_notification(&state, &msg).await;
             
             if return_url {
-                // 返回地址
-                let video_url = format!("/clips/{}", filename);
-                (StatusCode::OK, Json(serde_json::json!({ "video_url": video_url }))).into_response()
+                (StatusCode::OK, Json(ClipResponse { video_url })).into_response()
             } else {
-                // 直接返回视频流
-                match std::fs::read(&video_path) {
+                match tokio::fs::read(state.video_service.clip_path(&filename)).await {
                     Ok(data) => (
-                        [("Content-Type", "video/mp4")],
+                        [("Content-Type", options.container.content_type())],
                         data
                     ).into_response(),
                     Err(e) => (
@@ -150,15 +298,267 @@ pub async fn clip_video(
             }
         }
         Err(e) => {
-            // 飞书通知：截视频失败
+            // 错误信息会写入日志、发送到飞书并返回给调用方，去掉拉流地址中的凭据
+            let segment = e.segment;
+            let e = redact_text(&e.to_string());
+            tracing::error!("Failed to concat clips: {}", e);
+            
+            // 飞书通知：拼接失败
+            let msg = format!("【视频拼接失败】\n视频源: {}\n错误: {}", labels.join(", "), e);
+            send_feishu_notification(&state, &msg).await;
+            
+            let err = serde_json::json!({"error": format!("视频拼接失败: {}", e), "segment": segment});
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+        }
+    }
+}
+
+// 音频提取接口
+pub async fn extract_audio(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<AudioRequest>
+) -> impl IntoResponse {
+    let return_url = payload.return_url.unwrap_or(true);
+    
+    let prepared = payload.to_options().and_then(|options| {
+        let input = state.sources.resolve(payload.source_id.as_deref(), payload.url.as_deref())?;
+        Ok((options, input))
+    });
+    let (options, input) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    tracing::info!("Received audio extraction request for: {}", input.label);
+    
+    let start = payload.start.unwrap_or(0.0);
+    let profile = payload.profile.as_deref().or(input.profile.as_deref());
+    
+    let result = state.video_service
+        .extract_audio(&input.url, start, payload.duration, profile, &options)
+        .await;
+    match result {
+        Ok(filename) => {
+            record_source(&state, &filename, payload.source_id.as_deref());
+            if return_url {
+                let audio_url = format!("/clips/{}", filename);
+                (StatusCode::OK, Json(AudioResponse { audio_url })).into_response()
+            } else {
+                match tokio::fs::read(state.video_service.clip_path(&filename)).await {
+                    Ok(data) => (
+                        [("Content-Type", options.format.content_type())],
+                        data
+                    ).into_response(),
+                    Err(e) => (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("读取音频文件失败: {}", e)
+                    ).into_response(),
+                }
+            }
+        }
+        Err(e) => {
+            let e = redact_text(&e);
+            tracing::error!("Failed to extract audio: {}", e);
+            let err = serde_json::json!({"error": format!("音频提取失败: {}", e)});
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+        }
+    }
+}
+
+// 动图预览接口
+pub async fn create_preview(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<PreviewRequest>
+) -> impl IntoResponse {
+    let return_url = payload.return_url.unwrap_or(true);
+    
+    let prepared = payload.to_options().and_then(|mut options| {
+        let input = state.sources.resolve(payload.source_id.as_deref(), payload.url.as_deref())?;
+        options.masks = input.masks.clone();
+        Ok((options, input))
+    });
+    let (options, input) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    tracing::info!("Received preview request for: {}", input.label);
+    
+    let start = payload.start.unwrap_or(0.0);
+    let profile = payload.profile.as_deref().or(input.profile.as_deref());
+    
+    let result = state.video_service
+        .create_preview(&input.url, start, payload.duration, profile, &options)
+        .await;
+    match result {
+        Ok(filename) => {
+            record_source(&state, &filename, payload.source_id.as_deref());
+            let preview_url = format!("/clips/{}", filename);
+            
+            // 飞书通知：附上动图链接
             let msg = format!(
-                "【视频截取失败】\nURL: {}\n起始: {} 秒\n时长: {} 秒\n错误: {}",
-                payload.url, start, duration, e
+                "【动图预览】\n视频源: {}\n起始: {} 秒\n时长: {} 秒\n预览: {}",
+                input.label, start, payload.duration, public_url(&preview_url)
             );
             send_feishu_notification(&state, &msg).await;
             
-            let err = serde_json::json!({"error": format!("视频截取失败: {}", e)});
+            if return_url {
+                (StatusCode::OK, Json(PreviewResponse { preview_url })).into_response()
+            } else {
+                match tokio::fs::read(state.video_service.clip_path(&filename)).await {
+                    Ok(data) => (
+                        [("Content-Type", options.format.content_type())],
+                        data
+                    ).into_response(),
+                    Err(e) => (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("读取动图文件失败: {}", e)
+                    ).into_response(),
+                }
+            }
+        }
+        Err(e) => {
+            let e = redact_text(&e);
+            tracing::error!("Failed to create preview: {}", e);
+            
+            let msg = format!(
+                "【动图预览失败】\n视频源: {}\n起始: {} 秒\n时长: {} 秒\n错误: {}",
+                input.label, start, payload.duration, e
+            );
+            send_feishu_notification(&state, &msg).await;
+            
+            let err = serde_json::json!({"error": format!("动图生成失败: {}", e)});
             (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
         }
     }
-} 
\ No newline at end of file
+}
+
+// 获取摄像头配置列表
+pub async fn list_profiles(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (StatusCode::OK, Json(state.video_service.profiles().to_vec()))
+}
+
+// 获取命名编码配置列表
+pub async fn list_encodings(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (StatusCode::OK, Json(state.video_service.encodings().to_vec()))
+}
+
+// 探测视频流信息
+pub async fn probe_stream(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ProbeRequest>
+) -> impl IntoResponse {
+    let input = match state.sources.resolve(payload.source_id.as_deref(), payload.url.as_deref()) {
+        Ok(input) => input,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    tracing::info!("Received probe request for: {}", input.label);
+    
+    let profile = payload.profile.as_deref().or(input.profile.as_deref());
+    
+    match state.video_service.probe(&input.url, profile).await {
+        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+        Err(e) => {
+            let e = redact_text(&e);
+            tracing::error!("Failed to probe stream: {}", e);
+            let err = serde_json::json!({"error": format!("视频流探测失败: {}", e)});
+            (StatusCode::BAD_GATEWAY, Json(err)).into_response()
+        }
+    }
+}
+
+// clips目录下的剪辑文件作为输入，返回输入和输出文件名前缀
+fn clip_file_input(state: &AppState, filename: &str) -> Result<(StreamInput, String), String> {
+    if filename.is_empty() || filename.contains(['/', '\\']) || filename.starts_with('.') {
+        return Err(format!("无效的文件名: {}", filename));
+    }
+    let path = state.video_service.clip_path(filename);
+    if !std::path::Path::new(&path).is_file() {
+        return Err(format!("剪辑文件不存在: {}", filename));
+    }
+    let stem = filename.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(filename).to_string();
+
+    let input = StreamInput {
+        url: path,
+        profile: None,
+        label: filename.to_string(),
+        masks: Vec::new(),
+    };
+    Ok((input, stem))
+}
+
+/// 记录输出文件来自的视频源，清理时按视频源的保留策略处理
+fn record_source(state: &AppState, filename: &str, source_id: Option<&str>) {
+    if source_id.is_none() {
+        return;
+    }
+    if let Err(e) = state.clips.record(filename, source_id) {
+        tracing::warn!("Failed to record clip {}: {}", filename, e);
+    }
+}
+
+// 生成缩略图雪碧图和WebVTT索引
+pub async fn generate_sprite(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SpriteRequest>
+) -> impl IntoResponse {
+    // 从已有剪辑生成时沿用剪辑记录的视频源
+    let source_id = match &payload.filename {
+        Some(filename) => state.clips.get(filename).and_then(|clip| clip.source_id),
+        None => payload.source_id.clone(),
+    };
+    let prepared = payload.to_options().and_then(|mut options| {
+        let (input, stem) = match &payload.filename {
+            Some(_) if payload.url.is_some() || payload.source_id.is_some() => {
+                return Err("filename、url、source_id 只能指定一个".to_string());
+            }
+            Some(filename) => clip_file_input(&state, filename)?,
+            None => {
+                let input = state.sources.resolve(payload.source_id.as_deref(), payload.url.as_deref())?;
+                (input, uuid::Uuid::new_v4().to_string())
+            }
+        };
+        options.masks = input.masks.clone();
+        Ok((options, input, stem))
+    });
+    let (options, input, stem) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+    tracing::info!("Received sprite request for: {}", input.label);
+    
+    let profile = payload.profile.as_deref().or(input.profile.as_deref());
+    
+    match state.video_service.generate_sprite(&input.url, payload.duration, profile, &stem, &options).await {
+        Ok(sheet) => {
+            record_source(&state, &sheet.sprite_file, source_id.as_deref());
+            record_source(&state, &sheet.vtt_file, source_id.as_deref());
+            let response = SpriteResponse {
+                sprite_url: format!("/clips/{}", sheet.sprite_file),
+                vtt_url: format!("/clips/{}", sheet.vtt_file),
+                columns: sheet.columns,
+                rows: sheet.rows,
+                thumb_width: sheet.thumb_width,
+                thumb_height: sheet.thumb_height,
+                frames: sheet.frames,
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => {
+            let e = redact_text(&e);
+            tracing::error!("Failed to generate sprite: {}", e);
+            let err = serde_json::json!({"error": format!("雪碧图生成失败: {}", e)});
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+        }
+    }
+}
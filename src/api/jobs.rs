@@ -0,0 +1,174 @@
+use axum::{
+    response::{IntoResponse, Response, sse::{Event, KeepAlive, Sse}},
+    extract::{Json, Path, State},
+    http::StatusCode,
+};
+use futures_util::stream;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use crate::models::{AppState, ClipRequest, JobSubmitResponse, MosaicRequest};
+use crate::services::{ClipJobParams, JobParams, MosaicJobParams};
+use crate::utils::now_secs_f64;
+
+// 校验剪辑请求：参数范围、视频源、摄像头配置、输出编码，从录像剪辑时检查是否有录像
+//
+// 视频源登记的遮挡在执行时才合并，这里合并一次检查能否与请求的编码共存。
+// start为负数时按提交时刻换算为start_time，从预录缓冲或录像中剪辑
+pub(crate) fn prepare_clip_params(state: &AppState, payload: &ClipRequest) -> Result<ClipJobParams, String> {
+    let mut params = payload.to_job_params()?;
+    let input = state.sources.resolve(params.source_id.as_deref(), params.url.as_deref())?;
+    let profile = params.profile.as_deref().or(input.profile.as_deref());
+    state.video_service.validate_profile(profile)?;
+    state.video_service.validate_output(&params.output.with_masks(&input.masks))?;
+
+    let Some(source) = params.source_id.as_deref().and_then(|id| state.sources.get(id)) else {
+        return Ok(params);
+    };
+    let archive = state.archive_for(&source);
+    if params.start < 0.0 {
+        if !source.record && source.buffer_seconds.is_none() {
+            return Err("视频源未开启预录缓冲或录像，start 不能为负数".to_string());
+        }
+        if !source.record
+            && let Some(seconds) = source.buffer_seconds
+            && -params.start > seconds as f64
+        {
+            return Err(format!("start 不能早于预录缓冲的 {} 秒", seconds));
+        }
+        params.start_time = Some(now_secs_f64() + params.start);
+        params.start = 0.0;
+    }
+    if let Some(start_time) = params.start_time
+        && archive.segments(&source.id, start_time, start_time + params.duration).is_empty()
+    {
+        return Err("该时间段没有录像".to_string());
+    }
+    Ok(params)
+}
+
+// 校验画面拼接请求：参数范围、每路视频源和摄像头配置
+fn prepare_mosaic_params(state: &AppState, payload: &MosaicRequest) -> Result<MosaicJobParams, String> {
+    let params = payload.to_job_params()?;
+    for (i, input) in params.inputs.iter().enumerate() {
+        let resolved = state.sources.resolve(input.source_id.as_deref(), input.url.as_deref())
+            .map_err(|e| format!("第 {} 路输入: {}", i + 1, e))?;
+        let profile = input.profile.as_deref().or(resolved.profile.as_deref());
+        state.video_service.validate_profile(profile)
+            .map_err(|e| format!("第 {} 路输入: {}", i + 1, e))?;
+    }
+    Ok(params)
+}
+
+// 提交任务，返回202和任务ID
+fn submit_job(state: &AppState, params: impl Into<JobParams>) -> Response {
+    match state.job_queue.submit(params) {
+        Ok(job) => {
+            let resp = JobSubmitResponse {
+                status_url: format!("/api/jobs/{}", job.id),
+                job_id: job.id,
+                status: job.status,
+            };
+            (StatusCode::ACCEPTED, Json(resp)).into_response()
+        }
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::SERVICE_UNAVAILABLE, Json(err)).into_response()
+        }
+    }
+}
+
+// 提交异步剪辑任务，立即返回任务ID
+pub async fn submit_clip_job(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ClipRequest>
+) -> Response {
+    match prepare_clip_params(&state, &payload) {
+        Ok(params) => submit_job(&state, params),
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::BAD_REQUEST, Json(err)).into_response()
+        }
+    }
+}
+
+// 提交多路画面拼接任务，立即返回任务ID，进度和结果通过任务接口查询
+pub async fn submit_mosaic_job(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MosaicRequest>
+) -> Response {
+    match prepare_mosaic_params(&state, &payload) {
+        Ok(params) => submit_job(&state, params),
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::BAD_REQUEST, Json(err)).into_response()
+        }
+    }
+}
+
+// 查询任务状态
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>
+) -> Response {
+    match state.job_queue.get(&id) {
+        Some(job) => (StatusCode::OK, Json(job)).into_response(),
+        None => {
+            let err = serde_json::json!({"error": format!("任务不存在: {}", id)});
+            (StatusCode::NOT_FOUND, Json(err)).into_response()
+        }
+    }
+}
+
+// 取消任务，结束正在运行的ffmpeg进程并删除残缺输出
+pub async fn cancel_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>
+) -> Response {
+    if state.job_queue.get(&id).is_none() {
+        let err = serde_json::json!({"error": format!("任务不存在: {}", id)});
+        return (StatusCode::NOT_FOUND, Json(err)).into_response();
+    }
+    
+    match state.job_queue.cancel(&id) {
+        Ok(job) => (StatusCode::OK, Json(job)).into_response(),
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::CONFLICT, Json(err)).into_response()
+        }
+    }
+}
+
+// 以SSE推送任务进度
+//
+// 每次状态变化推送一个 `progress` 事件，任务结束时推送 `done` 事件后关闭连接
+pub async fn job_events(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>
+) -> Response {
+    let Some(rx) = state.job_queue.subscribe(&id) else {
+        let err = serde_json::json!({"error": format!("任务不存在: {}", id)});
+        return (StatusCode::NOT_FOUND, Json(err)).into_response();
+    };
+    
+    // 状态: (接收端, 是否首次, 是否已结束)
+    let events = stream::unfold((rx, true, false), |(mut rx, first, done)| async move {
+        if done {
+            return None;
+        }
+        if !first && rx.changed().await.is_err() {
+            return None;
+        }
+        
+        let job = rx.borrow_and_update().clone();
+        let finished = job.status.is_finished();
+        let event = Event::default()
+            .event(if finished { "done" } else { "progress" })
+            .json_data(&job)
+            .unwrap_or_else(|_| Event::default().event("error"));
+        
+        Some((Ok::<_, Infallible>(event), (rx, false, finished)))
+    });
+    
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
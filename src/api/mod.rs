@@ -1,5 +1,13 @@
+pub mod batch;
+pub mod clips;
 pub mod handlers;
+pub mod jobs;
 pub mod middleware;
+pub mod sources;
  
+pub use batch::*;
+pub use clips::*;
 pub use handlers::*;
-pub use middleware::*; 
\ No newline at end of file
+pub use jobs::*;
+pub use middleware::*;
+pub use sources::*;
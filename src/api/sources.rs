@@ -0,0 +1,138 @@
+use axum::{
+    response::{IntoResponse, Response},
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+};
+use std::sync::Arc;
+
+use crate::models::{AppState, RecordingListQuery, RecordingListResponse, SourceListQuery};
+use crate::services::{find_gaps, SourceInfo, SourceInput};
+
+// 校验视频源引用的摄像头配置
+fn validate_source_input(state: &AppState, input: &SourceInput) -> Result<(), String> {
+    state.video_service.validate_profile(input.profile.as_deref())
+}
+
+// 列出视频源，支持按标签过滤
+pub async fn list_sources(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SourceListQuery>
+) -> impl IntoResponse {
+    let sources: Vec<SourceInfo> = state.sources
+        .list(query.tag.as_deref())
+        .iter()
+        .map(|s| s.info())
+        .collect();
+    
+    (StatusCode::OK, Json(sources))
+}
+
+// 查询单个视频源
+pub async fn get_source(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>
+) -> Response {
+    match state.sources.get(&id) {
+        Some(source) => (StatusCode::OK, Json(source.info())).into_response(),
+        None => {
+            let err = serde_json::json!({"error": format!("视频源不存在: {}", id)});
+            (StatusCode::NOT_FOUND, Json(err)).into_response()
+        }
+    }
+}
+
+// 创建视频源
+pub async fn create_source(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SourceInput>
+) -> Response {
+    if let Err(e) = validate_source_input(&state, &payload) {
+        let err = serde_json::json!({"error": e});
+        return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+    }
+    
+    match state.sources.create(payload) {
+        Ok(source) => (StatusCode::CREATED, Json(source.info())).into_response(),
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::BAD_REQUEST, Json(err)).into_response()
+        }
+    }
+}
+
+// 更新视频源，未传密码时保留原密码
+pub async fn update_source(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(payload): Json<SourceInput>
+) -> Response {
+    if let Err(e) = validate_source_input(&state, &payload) {
+        let err = serde_json::json!({"error": e});
+        return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+    }
+    
+    match state.sources.update(&id, payload) {
+        Ok(Some(source)) => (StatusCode::OK, Json(source.info())).into_response(),
+        Ok(None) => {
+            let err = serde_json::json!({"error": format!("视频源不存在: {}", id)});
+            (StatusCode::NOT_FOUND, Json(err)).into_response()
+        }
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::BAD_REQUEST, Json(err)).into_response()
+        }
+    }
+}
+
+// 删除视频源
+pub async fn delete_source(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>
+) -> Response {
+    match state.sources.delete(&id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => {
+            let err = serde_json::json!({"error": format!("视频源不存在: {}", id)});
+            (StatusCode::NOT_FOUND, Json(err)).into_response()
+        }
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+        }
+    }
+}
+
+// 查询视频源在时间范围内的录像片段和中断，只开启预录缓冲的视频源返回缓冲中的片段
+pub async fn list_recordings(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<RecordingListQuery>
+) -> Response {
+    let Some(source) = state.sources.get(&id) else {
+        let err = serde_json::json!({"error": format!("视频源不存在: {}", id)});
+        return (StatusCode::NOT_FOUND, Json(err)).into_response();
+    };
+    let (from, to) = match query.range() {
+        Ok(range) => range,
+        Err(e) => {
+            let err = serde_json::json!({"error": e});
+            return (StatusCode::BAD_REQUEST, Json(err)).into_response();
+        }
+    };
+
+    let index = state.archive_for(&source);
+    index.refresh(&id);
+    let segments = index.segments(&id, from, to);
+    let gaps = find_gaps(&segments, from, to);
+    let missing: f64 = gaps.iter().map(|gap| gap.end - gap.start).sum();
+
+    let resp = RecordingListResponse {
+        source_id: id,
+        from,
+        to,
+        recorded_seconds: to - from - missing,
+        segments,
+        gaps,
+    };
+    (StatusCode::OK, Json(resp)).into_response()
+}
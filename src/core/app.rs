@@ -10,7 +10,13 @@ use tower_http::cors::CorsLayer;
 
 use crate::core::config::AppConfig;
 use crate::models::AppState;
-use crate::api::{take_snapshot, clip_video, get_concurrent_requests, get_system_stats, track_concurrent_requests};
+use crate::api::{
+    take_snapshot, take_snapshot_batch, clip_video, get_concurrent_requests, get_system_stats, track_concurrent_requests,
+    submit_clip_job, submit_mosaic_job, get_job, cancel_job, job_events, list_profiles, list_encodings,
+    probe_stream, generate_sprite, create_preview, extract_audio, concat_clips,
+    list_sources, get_source, create_source, update_source, delete_source, list_recordings,
+    get_clip, pin_clip, unpin_clip,
+};
 
 /// 视频服务器应用
 /// 
@@ -79,15 +85,30 @@ impl VideoServerApp {
             // API路由
             .route("/api/hello", get(|| async { "Hello from Video Server API!" }))
             .route("/api/snapshot", post(take_snapshot))
+            .route("/api/snapshot/batch", post(take_snapshot_batch))
             .route("/api/clip", post(clip_video))
+            .route("/api/clip/concat", post(concat_clips))
+            .route("/api/audio", post(extract_audio))
+            .route("/api/probe", post(probe_stream))
+            .route("/api/sprite", post(generate_sprite))
+            .route("/api/preview", post(create_preview))
+            .route("/api/jobs", post(submit_clip_job))
+            .route("/api/jobs/mosaic", post(submit_mosaic_job))
+            .route("/api/jobs/{id}", get(get_job).delete(cancel_job))
+            .route("/api/jobs/{id}/events", get(job_events))
+            .route("/api/profiles", get(list_profiles))
+            .route("/api/encodings", get(list_encodings))
+            .route("/api/sources", get(list_sources).post(create_source))
+            .route("/api/sources/{id}", get(get_source).put(update_source).delete(delete_source))
+            .route("/api/sources/{id}/recordings", get(list_recordings))
+            .route("/api/clips/{name}", get(get_clip))
+            .route("/api/clips/{name}/pin", post(pin_clip))
+            .route("/api/clips/{name}/unpin", post(unpin_clip))
             .route("/api/concurrent", get(get_concurrent_requests))
             .route("/api/system-stats", get(get_system_stats))
             
-            // 静态文件服务 - clips目录
-            .nest_service(
-                &format!("/{}", &config.clips_dir), 
-                ServeDir::new(&config.clips_dir)
-            )
+            // 静态文件服务 - clips目录，路径固定为/clips，与CLIPS_DIR的实际位置无关
+            .nest_service("/clips", ServeDir::new(&config.clips_dir))
             
             // 前端静态文件服务（fallback）
             .fallback_service(ServeDir::new(&config.frontend_dir))
@@ -129,10 +150,26 @@ impl VideoServerApp {
         println!("🔗 Available API Endpoints:");
         println!("   GET  {}/api/hello         - 健康检查", base_url);
         println!("   POST {}/api/snapshot      - 视频截图", base_url);
+        println!("   POST {}/api/snapshot/batch - 多帧截图", base_url);
         println!("   POST {}/api/clip          - 视频剪辑", base_url);
+        println!("   POST {}/api/clip/concat   - 多段拼接", base_url);
+        println!("   POST {}/api/audio         - 音频提取", base_url);
+        println!("   POST {}/api/probe         - 视频流探测", base_url);
+        println!("   POST {}/api/sprite        - 缩略图雪碧图", base_url);
+        println!("   POST {}/api/preview       - 动图预览", base_url);
+        println!("   POST {}/api/jobs          - 提交异步剪辑任务", base_url);
+        println!("   POST {}/api/jobs/mosaic   - 提交多路画面拼接任务", base_url);
+        println!("   GET  {}/api/jobs/{{id}}     - 查询任务状态", base_url);
+        println!("   DELETE {}/api/jobs/{{id}}   - 取消任务", base_url);
+        println!("   GET  {}/api/jobs/{{id}}/events - 任务进度推送(SSE)", base_url);
+        println!("   GET  {}/api/profiles      - 摄像头配置列表", base_url);
+        println!("   GET  {}/api/encodings     - 编码配置列表", base_url);
+        println!("   GET/POST {}/api/sources   - 视频源列表/创建", base_url);
+        println!("   GET/PUT/DELETE {}/api/sources/{{id}} - 视频源查询/更新/删除", base_url);
+        println!("   POST {}/api/clips/{{name}}/pin - 固定剪辑，不被自动清理（unpin 取消）", base_url);
         println!("   GET  {}/api/concurrent    - 并发请求统计", base_url);
         println!("   GET  {}/api/system-stats  - 系统状态监控", base_url);
-        println!("   GET  {}/{}/*              - 视频片段文件", base_url, self.config.clips_dir);
+        println!("   GET  {}/clips/*           - 视频片段文件", base_url);
         println!("   GET  {}/*                 - 前端静态文件", base_url);
         println!();
     }
@@ -1,10 +1,13 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::AtomicUsize;
 
-use crate::core::config::AppConfig;
-use crate::core::app::VideoServerApp;
+use crate::core::{AppConfig, VideoServerApp};
 use crate::models::AppState;
-use crate::services::video::VideoSnapshotService;
+use crate::services::{
+    VideoSnapshotService, StreamHandlerRegistry, ProfileRegistry, EncodingRegistry, JobQueue, SourceStore, RecordingIndex,
+    ClipCatalog, RetentionRules, spawn_job_workers, spawn_recorders, spawn_retention,
+};
 
 /// 应用构建器
 /// 
@@ -25,6 +28,13 @@ use crate::services::video::VideoSnapshotService;
 /// ```
 pub struct AppBuilder {
     config: AppConfig,
+    profiles: ProfileRegistry,
+    encodings: EncodingRegistry,
+    sources: SourceStore,
+    recordings: RecordingIndex,
+    buffers: RecordingIndex,
+    clips: ClipCatalog,
+    retention: RetentionRules,
 }
 
 impl AppBuilder {
@@ -32,17 +42,30 @@ impl AppBuilder {
     /// 
     /// 使用默认配置初始化构建器
     pub fn new() -> Self {
-        Self {
-            config: AppConfig::default(),
-        }
+        Self::with_initial_config(AppConfig::default())
     }
 
     /// 从环境变量创建构建器
     /// 
     /// 自动从环境变量加载配置
     pub fn from_env() -> Self {
+        Self::with_initial_config(AppConfig::from_env())
+    }
+
+    fn with_initial_config(config: AppConfig) -> Self {
+        let sources = SourceStore::new(&config.sources_file);
+        let recordings = RecordingIndex::new(&config.recordings_dir);
+        let buffers = RecordingIndex::new(&config.buffer_dir);
+        let clips = ClipCatalog::new(&config.clip_metadata_file);
         Self {
-            config: AppConfig::from_env(),
+            config,
+            profiles: ProfileRegistry::default(),
+            encodings: EncodingRegistry::default(),
+            sources,
+            recordings,
+            buffers,
+            clips,
+            retention: RetentionRules::default(),
         }
     }
 
@@ -50,6 +73,7 @@ impl AppBuilder {
     /// 
     /// # Arguments
     /// * `config` - 应用配置实例
+    #[allow(dead_code)]
     pub fn with_config(mut self, config: AppConfig) -> Self {
         self.config = config;
         self
@@ -62,7 +86,12 @@ impl AppBuilder {
     /// - 创建必要目录
     /// - 初始化日志系统
     /// - 验证配置
-    pub fn init_environment(self) -> Result<Self, String> {
+    /// - 加载摄像头配置
+    /// - 加载编码配置
+    /// - 加载视频源目录
+    /// - 扫描录像和预录缓冲索引
+    /// - 加载剪辑元数据和保留策略
+    pub fn init_environment(mut self) -> Result<Self, String> {
         // 加载环境变量文件
         dotenv::dotenv().ok();
         
@@ -77,6 +106,23 @@ impl AppBuilder {
         // 初始化日志系统
         self.init_logging();
         
+        // 加载摄像头配置
+        self.profiles = ProfileRegistry::load(&self.config.profiles_file)?;
+        
+        // 加载编码配置
+        self.encodings = EncodingRegistry::load(&self.config.encodings_file)?;
+        
+        // 加载视频源目录
+        self.sources = SourceStore::load(&self.config.sources_file)?;
+
+        // 扫描录像和预录缓冲索引，过期的缓冲由录像管理协程清理
+        self.recordings = RecordingIndex::load(&self.config.recordings_dir)?;
+        self.buffers = RecordingIndex::load(&self.config.buffer_dir)?;
+
+        // 加载剪辑元数据和保留策略
+        self.clips = ClipCatalog::load(&self.config.clip_metadata_file)?;
+        self.retention = RetentionRules::load(&self.config.retention_file)?;
+        
         tracing::info!("环境初始化完成");
         tracing::debug!("配置信息: {:?}", self.config);
         
@@ -91,7 +137,18 @@ impl AppBuilder {
         
         Arc::new(AppState {
             concurrent_requests: Arc::new(AtomicUsize::new(0)),
-            video_service: VideoSnapshotService::new(),
+            video_service: VideoSnapshotService::new(
+                StreamHandlerRegistry::default(),
+                self.profiles.clone(),
+                self.encodings.clone(),
+            )
+            .with_watermark_dir(&self.config.watermark_dir)
+            .with_clips_dir(&self.config.clips_dir),
+            job_queue: JobQueue::new(self.config.job_queue_size),
+            sources: self.sources.clone(),
+            recordings: self.recordings.clone(),
+            buffers: self.buffers.clone(),
+            clips: self.clips.clone(),
         })
     }
 
@@ -107,6 +164,15 @@ impl AppBuilder {
         // 构建应用状态
         let app_state = self.build_app_state();
         
+        // 启动剪辑任务工作协程
+        spawn_job_workers(app_state.clone(), self.config.job_workers);
+
+        // 启动录像管理协程
+        spawn_recorders(app_state.clone(), self.config.record_segment_secs);
+
+        // 启动保留策略清理协程
+        spawn_retention(app_state.clone(), self.retention.clone(), PathBuf::from(&self.config.clips_dir));
+        
         // 创建应用实例
         let app = VideoServerApp::new(self.config, app_state);
         
@@ -8,6 +8,28 @@ pub struct AppConfig {
     pub port: u16,
     pub clips_dir: String,
     pub frontend_dir: String,
+    /// 剪辑任务工作线程数
+    pub job_workers: usize,
+    /// 剪辑任务队列容量
+    pub job_queue_size: usize,
+    /// 摄像头配置文件路径
+    pub profiles_file: String,
+    /// 编码配置文件路径
+    pub encodings_file: String,
+    /// 视频源目录文件路径
+    pub sources_file: String,
+    /// 水印图片目录
+    pub watermark_dir: String,
+    /// 录像目录，每个视频源一个子目录
+    pub recordings_dir: String,
+    /// 录像片段时长（秒）
+    pub record_segment_secs: u64,
+    /// 预录缓冲目录，可以指向tmpfs
+    pub buffer_dir: String,
+    /// 保留策略配置文件路径
+    pub retention_file: String,
+    /// 剪辑元数据文件路径
+    pub clip_metadata_file: String,
 }
 
 impl Default for AppConfig {
@@ -17,12 +39,24 @@ impl Default for AppConfig {
             port: 3000,
             clips_dir: "clips".to_string(),
             frontend_dir: "frontend/vue-project/dist".to_string(),
+            job_workers: 2,
+            job_queue_size: 1000,
+            profiles_file: "camera_profiles.json".to_string(),
+            encodings_file: "encoding_profiles.json".to_string(),
+            sources_file: "data/sources.json".to_string(),
+            watermark_dir: "watermarks".to_string(),
+            recordings_dir: "recordings".to_string(),
+            record_segment_secs: 60,
+            buffer_dir: "buffers".to_string(),
+            retention_file: "retention.json".to_string(),
+            clip_metadata_file: "data/clips.json".to_string(),
         }
     }
 }
 
 impl AppConfig {
     /// 创建新的配置
+    #[allow(dead_code)]
     pub fn new() -> Self {
         Self::default()
     }
@@ -32,10 +66,10 @@ impl AppConfig {
         let mut config = Self::default();
         
         // 从环境变量读取配置
-        if let Ok(port) = env::var("SERVER_PORT") {
-            if let Ok(port_num) = port.parse::<u16>() {
-                config.port = port_num;
-            }
+        if let Ok(port) = env::var("SERVER_PORT")
+            && let Ok(port_num) = port.parse::<u16>()
+        {
+            config.port = port_num;
         }
         
         if let Ok(clips_dir) = env::var("CLIPS_DIR") {
@@ -46,34 +80,88 @@ impl AppConfig {
             config.frontend_dir = frontend_dir;
         }
         
-        if let Ok(host) = env::var("SERVER_HOST") {
-            if let Ok(addr) = host.parse::<std::net::Ipv4Addr>() {
-                config.host = addr.octets();
-            }
+        if let Ok(workers) = env::var("JOB_WORKERS")
+            && let Ok(workers_num) = workers.parse::<usize>()
+        {
+            config.job_workers = workers_num;
+        }
+
+        if let Ok(queue_size) = env::var("JOB_QUEUE_SIZE")
+            && let Ok(queue_size_num) = queue_size.parse::<usize>()
+        {
+            config.job_queue_size = queue_size_num;
+        }
+
+        if let Ok(profiles_file) = env::var("CAMERA_PROFILES_FILE") {
+            config.profiles_file = profiles_file;
+        }
+
+        if let Ok(encodings_file) = env::var("ENCODING_PROFILES_FILE") {
+            config.encodings_file = encodings_file;
+        }
+
+        if let Ok(sources_file) = env::var("SOURCES_FILE") {
+            config.sources_file = sources_file;
+        }
+
+        if let Ok(watermark_dir) = env::var("WATERMARK_DIR") {
+            config.watermark_dir = watermark_dir;
+        }
+
+        if let Ok(recordings_dir) = env::var("RECORDINGS_DIR") {
+            config.recordings_dir = recordings_dir;
+        }
+
+        if let Ok(segment_secs) = env::var("RECORD_SEGMENT_SECONDS")
+            && let Ok(segment_secs_num) = segment_secs.parse::<u64>()
+        {
+            config.record_segment_secs = segment_secs_num;
+        }
+
+        if let Ok(buffer_dir) = env::var("BUFFER_DIR") {
+            config.buffer_dir = buffer_dir;
+        }
+
+        if let Ok(retention_file) = env::var("RETENTION_FILE") {
+            config.retention_file = retention_file;
+        }
+
+        if let Ok(clip_metadata_file) = env::var("CLIP_METADATA_FILE") {
+            config.clip_metadata_file = clip_metadata_file;
+        }
+
+        if let Ok(host) = env::var("SERVER_HOST")
+            && let Ok(addr) = host.parse::<std::net::Ipv4Addr>()
+        {
+            config.host = addr.octets();
         }
         
         config
     }
 
     /// 设置监听地址
+    #[allow(dead_code)]
     pub fn with_host(mut self, host: [u8; 4]) -> Self {
         self.host = host;
         self
     }
 
     /// 设置端口
+    #[allow(dead_code)]
     pub fn with_port(mut self, port: u16) -> Self {
         self.port = port;
         self
     }
 
     /// 设置视频片段存储目录
+    #[allow(dead_code)]
     pub fn with_clips_dir(mut self, dir: &str) -> Self {
         self.clips_dir = dir.to_string();
         self
     }
 
     /// 设置前端静态文件目录
+    #[allow(dead_code)]
     pub fn with_frontend_dir(mut self, dir: &str) -> Self {
         self.frontend_dir = dir.to_string();
         self
@@ -97,6 +185,34 @@ impl AppConfig {
         if self.frontend_dir.is_empty() {
             return Err("frontend目录不能为空".to_string());
         }
+
+        if self.sources_file.is_empty() {
+            return Err("视频源文件路径不能为空".to_string());
+        }
+
+        if self.clip_metadata_file.is_empty() {
+            return Err("剪辑元数据文件路径不能为空".to_string());
+        }
+
+        if self.job_workers == 0 {
+            return Err("任务工作线程数不能为0".to_string());
+        }
+
+        if self.job_queue_size == 0 {
+            return Err("任务队列容量不能为0".to_string());
+        }
+
+        if self.recordings_dir.is_empty() {
+            return Err("录像目录不能为空".to_string());
+        }
+
+        if self.buffer_dir.is_empty() {
+            return Err("预录缓冲目录不能为空".to_string());
+        }
+
+        if !(10..=3600).contains(&self.record_segment_secs) {
+            return Err("录像片段时长必须在 10-3600 秒之间".to_string());
+        }
         
         Ok(())
     }
@@ -104,6 +220,8 @@ impl AppConfig {
     /// 创建必要的目录结构
     pub fn ensure_directories(&self) -> Result<(), std::io::Error> {
         std::fs::create_dir_all(&self.clips_dir)?;
+        std::fs::create_dir_all(&self.recordings_dir)?;
+        std::fs::create_dir_all(&self.buffer_dir)?;
         Ok(())
     }
 
@@ -114,7 +232,18 @@ impl AppConfig {
         println!("   - Port: {}", self.port);
         println!("   - Clips directory: {}", self.clips_dir);
         println!("   - Frontend directory: {}", self.frontend_dir);
+        println!("   - Job workers: {}", self.job_workers);
+        println!("   - Job queue size: {}", self.job_queue_size);
+        println!("   - Camera profiles file: {}", self.profiles_file);
+        println!("   - Encoding profiles file: {}", self.encodings_file);
+        println!("   - Sources file: {}", self.sources_file);
+        println!("   - Watermark directory: {}", self.watermark_dir);
+        println!("   - Recordings directory: {}", self.recordings_dir);
+        println!("   - Record segment seconds: {}", self.record_segment_secs);
+        println!("   - Buffer directory: {}", self.buffer_dir);
+        println!("   - Retention file: {}", self.retention_file);
+        println!("   - Clip metadata file: {}", self.clip_metadata_file);
         println!("   - Socket address: {}", self.socket_addr());
-        println!("   - Use env vars: SERVER_HOST, SERVER_PORT, CLIPS_DIR, FRONTEND_DIR");
+        println!("   - Use env vars: SERVER_HOST, SERVER_PORT, CLIPS_DIR, FRONTEND_DIR, JOB_WORKERS, JOB_QUEUE_SIZE, CAMERA_PROFILES_FILE, ENCODING_PROFILES_FILE, SOURCES_FILE, WATERMARK_DIR, RECORDINGS_DIR, RECORD_SEGMENT_SECONDS, BUFFER_DIR, RETENTION_FILE, CLIP_METADATA_FILE");
     }
 } 
\ No newline at end of file
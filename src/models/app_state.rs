@@ -1,10 +1,26 @@
 use std::sync::Arc;
 use std::sync::atomic::AtomicUsize;
-use crate::services::video::VideoSnapshotService;
+use crate::services::{VideoSnapshotService, JobQueue, SourceStore, RecordingIndex, Source, ClipCatalog};
 
 // 应用状态结构体
 #[derive(Clone)]
 pub struct AppState {
     pub concurrent_requests: Arc<AtomicUsize>,
     pub video_service: VideoSnapshotService,
-} 
\ No newline at end of file
+    pub job_queue: JobQueue,
+    pub sources: SourceStore,
+    pub recordings: RecordingIndex,
+    pub buffers: RecordingIndex,
+    pub clips: ClipCatalog,
+}
+
+impl AppState {
+    /// 视频源过去画面所在的索引：只开启预录缓冲的视频源使用缓冲，否则使用录像（包括关闭录像前保留的录像）
+    pub fn archive_for(&self, source: &Source) -> &RecordingIndex {
+        if !source.record && source.buffer_seconds.is_some() {
+            &self.buffers
+        } else {
+            &self.recordings
+        }
+    }
+}
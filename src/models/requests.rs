@@ -1,16 +1,548 @@
 use serde::Deserialize;
 
+use crate::utils::{now_secs_f64, parse_timestamp};
+use crate::services::{
+    AudioFormat, AudioOptions, ClipJobParams, ConcatOptions, ClipOutput, ClipPackage, CodecPreset, CropRegion, FitMode, MosaicJobInput, MosaicJobParams, MosaicLayout,
+    MosaicOptions, OutputContainer, OverlaySpec, PrivacyMask, PreviewFormat, PreviewOptions, SnapshotFormat, SnapshotOptions, SpriteOptions,
+};
+
+/// 墙上时间，可以是Unix秒或带时区的RFC 3339字符串
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum WallClock {
+    Unix(f64),
+    Text(String),
+}
+
+impl WallClock {
+    /// 转换为Unix秒
+    pub fn to_unix(&self) -> Result<f64, String> {
+        match self {
+            Self::Unix(secs) if secs.is_finite() && *secs > 0.0 => Ok(*secs),
+            Self::Unix(secs) => Err(format!("无效的时间: {}", secs)),
+            Self::Text(text) => parse_timestamp(text),
+        }
+    }
+}
+
 // 请求体结构体
 #[derive(Deserialize)]
 pub struct SnapshotRequest {
-    pub url: String,
+    pub url: Option<String>, // 拉流地址，与source_id二选一
+    pub source_id: Option<String>, // 已登记的视频源ID
     pub timestamp: Option<f64>, // 可选的时间戳，单位秒
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+    pub on_error: Option<String>, // 截图失败时的返回方式 image/json，默认image
+    #[serde(flatten)]
+    pub image: ImageParams, // 图片格式、质量和尺寸
+}
+
+// 截图图片输出参数，截图和多帧截图共用
+#[derive(Deserialize)]
+pub struct ImageParams {
+    pub format: Option<String>, // 图片格式 png/jpeg/webp，默认png
+    pub quality: Option<u8>, // 图片质量 1-100，只对jpeg/webp生效
+    pub width: Option<u32>, // 输出宽度，只指定一边时按比例缩放
+    pub height: Option<u32>, // 输出高度
+    pub fit: Option<String>, // 同时指定宽高时的缩放方式 contain/cover/fill，默认contain
+    pub masks: Option<Vec<PrivacyMask>>, // 隐私遮挡，视频源登记的遮挡总会生效
+    pub crop: Option<CropRegion>, // 裁剪区域，在缩放之前执行，配合宽高实现数字变焦
+    pub overlay: Option<OverlaySpec>, // 叠加文字、时间和水印
+}
+
+// 多帧截图：timestamps 与 interval/count 二选一
+#[derive(Deserialize)]
+pub struct SnapshotBatchRequest {
+    pub url: Option<String>, // 拉流地址，与source_id二选一
+    pub source_id: Option<String>, // 已登记的视频源ID
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+    pub timestamps: Option<Vec<f64>>, // 截图时间戳列表，单位秒
+    pub start: Option<f64>, // 按间隔截图时的起始时间，默认0
+    pub interval: Option<f64>, // 截图间隔，单位秒
+    pub count: Option<usize>, // 按间隔截图的帧数
+    pub output: Option<String>, // 返回方式 zip/multipart/json，默认zip
+    #[serde(flatten)]
+    pub image: ImageParams,
 }
 
 #[derive(Deserialize)]
 pub struct ClipRequest {
-    pub url: String,
-    pub start: Option<f64>,
-    pub duration: f64,
+    pub url: Option<String>, // 拉流地址，与source_id二选一
+    pub source_id: Option<String>, // 已登记的视频源ID
+    pub start: Option<f64>, // 起始时间，单位秒，默认0；为负数时从预录缓冲或录像中剪辑当前时刻之前的画面，只能用于source_id
+    pub start_time: Option<WallClock>, // 从录像中剪辑的开始时间（Unix秒或RFC 3339），与start互斥，只能用于source_id
+    pub end_time: Option<WallClock>, // 从录像中剪辑的结束时间，与duration二选一
+    pub duration: Option<f64>, // 持续时长，单位秒
     pub return_url: Option<bool>, // 新增
-} 
\ No newline at end of file
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+    pub container: Option<String>, // 输出容器 mp4/mkv/webm/mov/ts，默认mp4
+    pub codec: Option<String>, // 视频编码预设 copy/h264/h265/vp9/av1
+    pub encoding: Option<String>, // 命名编码配置，容器和编码未指定时使用配置中的值
+    pub package: Option<String>, // 打包为 hls/dash，输出到clips下的子目录
+    pub masks: Option<Vec<PrivacyMask>>, // 隐私遮挡，视频源登记的遮挡总会生效，会重新编码
+    pub crop: Option<CropRegion>, // 裁剪区域，会重新编码
+    pub width: Option<u32>, // 输出宽度（偶数），只指定一边时按比例缩放，会重新编码
+    pub height: Option<u32>, // 输出高度（偶数）
+    pub overlay: Option<OverlaySpec>, // 叠加文字、时间和水印，会重新编码
+}
+
+#[derive(Deserialize)]
+pub struct ProbeRequest {
+    pub url: Option<String>, // 拉流地址，与source_id二选一
+    pub source_id: Option<String>, // 已登记的视频源ID
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+}
+
+// 拼接请求中的一段
+#[derive(Deserialize)]
+pub struct ConcatSegmentRequest {
+    pub url: Option<String>, // 拉流地址，与source_id二选一
+    pub source_id: Option<String>, // 已登记的视频源ID
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+    pub start: Option<f64>, // 起始时间，单位秒，默认0
+    pub duration: f64, // 时长，单位秒
+}
+
+// 多段拼接请求，按segments顺序拼接
+#[derive(Deserialize)]
+pub struct ConcatRequest {
+    pub segments: Vec<ConcatSegmentRequest>,
+    pub width: Option<u32>, // 输出宽度，默认1280
+    pub height: Option<u32>, // 输出高度，默认720
+    pub fps: Option<u32>, // 输出帧率，默认25
+    pub container: Option<String>, // 输出容器 mp4/mkv/mov/ts，默认mp4
+    pub return_url: Option<bool>, // 默认true，false时直接返回视频
+}
+
+// 画面拼接请求中的一路输入
+#[derive(Deserialize)]
+pub struct MosaicInputRequest {
+    pub url: Option<String>, // 拉流地址，与source_id二选一
+    pub source_id: Option<String>, // 已登记的视频源ID
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+    pub label: Option<String>, // 格内显示的名称，默认使用视频源名称
+}
+
+// 多路画面拼接任务请求，按inputs顺序从左到右、从上到下排列
+#[derive(Deserialize)]
+pub struct MosaicRequest {
+    pub inputs: Vec<MosaicInputRequest>,
+    pub layout: String, // 布局 1x2(side-by-side)/2x2/3x3
+    pub duration: f64, // 时长，单位秒
+    pub width: Option<u32>, // 输出宽度，默认1280
+    pub height: Option<u32>, // 输出高度，默认720
+    pub timestamp: Option<bool>, // 是否叠加服务器时间，默认false
+}
+
+// 音频提取请求
+#[derive(Deserialize)]
+pub struct AudioRequest {
+    pub url: Option<String>, // 拉流地址，与source_id二选一
+    pub source_id: Option<String>, // 已登记的视频源ID
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+    pub start: Option<f64>, // 起始时间，单位秒，默认0
+    pub duration: f64, // 时长，单位秒
+    pub format: Option<String>, // 音频格式 wav/flac/mp3/opus，默认wav
+    pub sample_rate: Option<u32>, // 采样率，默认保持原采样率
+    pub channels: Option<u32>, // 声道数，1为下混为单声道
+    pub loudnorm: Option<bool>, // 是否做响度标准化，默认false
+    pub bitrate: Option<u32>, // mp3/opus码率，单位kbps
+    pub return_url: Option<bool>, // 默认true，false时直接返回音频
+}
+
+// 动图预览请求
+#[derive(Deserialize)]
+pub struct PreviewRequest {
+    pub url: Option<String>, // 拉流地址，与source_id二选一
+    pub source_id: Option<String>, // 已登记的视频源ID
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+    pub start: Option<f64>, // 起始时间，单位秒，默认0
+    pub duration: f64, // 时长，单位秒
+    pub fps: Option<u32>, // 帧率，默认10
+    pub width: Option<u32>, // 宽度，默认320
+    pub format: Option<String>, // 动图格式 gif/webp，默认gif
+    pub return_url: Option<bool>, // 默认true，false时直接返回动图
+}
+
+// 雪碧图请求：clips目录下的文件、url、source_id 三选一
+#[derive(Deserialize)]
+pub struct SpriteRequest {
+    pub filename: Option<String>, // clips目录下已生成的剪辑文件名
+    pub url: Option<String>, // 拉流地址
+    pub source_id: Option<String>, // 已登记的视频源ID
+    pub profile: Option<String>, // 可选的摄像头配置名称，不指定时按URL匹配
+    pub duration: Option<f64>, // 取样时长，单位秒，文件可省略，实时流必须指定
+    pub count: Option<usize>, // 缩略图数量，默认20
+    pub interval: Option<f64>, // 取样间隔，单位秒，指定后按间隔计算数量
+    pub columns: Option<u32>, // 每行缩略图数量，默认5
+    pub width: Option<u32>, // 缩略图宽度，默认160
+}
+
+// 视频源列表查询参数
+#[derive(Deserialize)]
+pub struct SourceListQuery {
+    pub tag: Option<String>,
+}
+
+// 固定剪辑请求，请求体可以省略
+#[derive(Deserialize, Default)]
+pub struct PinClipRequest {
+    pub until: Option<WallClock>, // 法律保全到期时间（Unix秒或RFC 3339），不指定时一直保留
+    pub reason: Option<String>, // 固定原因，例如案件编号
+}
+
+impl PinClipRequest {
+    /// 解析到期时间，必须晚于当前时间
+    pub fn until(&self) -> Result<Option<u64>, String> {
+        let Some(until) = self.until.as_ref().map(WallClock::to_unix).transpose()? else {
+            return Ok(None);
+        };
+        if until <= now_secs_f64() {
+            return Err("until 必须晚于当前时间".to_string());
+        }
+        Ok(Some(until.ceil() as u64))
+    }
+}
+
+// 录像查询参数，时间为Unix秒或带时区的RFC 3339
+#[derive(Deserialize)]
+pub struct RecordingListQuery {
+    pub from: Option<String>, // 默认 to 之前24小时
+    pub to: Option<String>, // 默认当前时间
+}
+
+/// 录像查询未指定开始时间时的范围（秒）
+const DEFAULT_RECORDING_RANGE: f64 = 86400.0;
+
+impl RecordingListQuery {
+    /// 解析查询范围 `(from, to)`
+    pub fn range(&self) -> Result<(f64, f64), String> {
+        let to = match &self.to {
+            Some(to) => parse_timestamp(to)?,
+            None => now_secs_f64(),
+        };
+        let from = match &self.from {
+            Some(from) => parse_timestamp(from)?,
+            None => to - DEFAULT_RECORDING_RANGE,
+        };
+        if from >= to {
+            return Err("from 必须早于 to".to_string());
+        }
+        Ok((from, to))
+    }
+}
+
+/// 单次多帧截图的最大帧数
+pub const MAX_BATCH_FRAMES: usize = 100;
+
+impl ImageParams {
+    /// 校验并转换为截图输出参数
+    pub fn to_options(&self) -> Result<SnapshotOptions, String> {
+        let options = SnapshotOptions {
+            format: self.format.as_deref().map(SnapshotFormat::parse).transpose()?.unwrap_or_default(),
+            quality: self.quality,
+            width: self.width,
+            height: self.height,
+            fit: self.fit.as_deref().map(FitMode::parse).transpose()?.unwrap_or_default(),
+            masks: self.masks.clone().unwrap_or_default(),
+            crop: self.crop.clone(),
+            overlay: self.overlay.clone(),
+        };
+        options.validate()?;
+        Ok(options)
+    }
+}
+
+impl SnapshotBatchRequest {
+    /// 校验并展开为截图时间戳列表
+    pub fn to_timestamps(&self) -> Result<Vec<f64>, String> {
+        let timestamps = match (&self.timestamps, self.interval) {
+            (Some(_), Some(_)) => return Err("timestamps 与 interval 不能同时指定".to_string()),
+            (Some(timestamps), None) => timestamps.clone(),
+            (None, Some(interval)) => {
+                if !interval.is_finite() || interval <= 0.0 {
+                    return Err("interval 必须大于0".to_string());
+                }
+                let start = self.start.unwrap_or(0.0);
+                let count = self.count.ok_or("按间隔截图时必须指定 count")?;
+                if count > MAX_BATCH_FRAMES {
+                    return Err(format!("最多截取 {} 帧", MAX_BATCH_FRAMES));
+                }
+                (0..count).map(|i| start + interval * i as f64).collect()
+            }
+            (None, None) => return Err("必须指定 timestamps 或 interval".to_string()),
+        };
+
+        if timestamps.is_empty() {
+            return Err("至少需要一个时间戳".to_string());
+        }
+        if timestamps.len() > MAX_BATCH_FRAMES {
+            return Err(format!("最多截取 {} 帧", MAX_BATCH_FRAMES));
+        }
+        if timestamps.iter().any(|t| !t.is_finite() || *t < 0.0) {
+            return Err("时间戳必须是非负数".to_string());
+        }
+
+        Ok(timestamps)
+    }
+}
+
+impl ClipRequest {
+    /// 校验参数并转换为剪辑任务参数
+    pub fn to_job_params(&self) -> Result<ClipJobParams, String> {
+        let start = self.start.unwrap_or(0.0);
+        if !start.is_finite() {
+            return Err("start 必须是有效数字".to_string());
+        }
+        if start < 0.0 && self.source_id.is_none() {
+            return Err("start 为负数时必须指定 source_id".to_string());
+        }
+        let start_time = self.start_time.as_ref().map(WallClock::to_unix).transpose()?;
+        if start_time.is_some() {
+            if self.start.is_some() {
+                return Err("start_time 与 start 不能同时指定".to_string());
+            }
+            if self.source_id.is_none() {
+                return Err("从录像剪辑必须指定 source_id".to_string());
+            }
+        }
+        let end_time = self.end_time.as_ref().map(WallClock::to_unix).transpose()?;
+        let duration = match (self.duration, start_time, end_time) {
+            (Some(_), _, Some(_)) => return Err("duration 与 end_time 不能同时指定".to_string()),
+            (Some(duration), _, None) => duration,
+            (None, Some(start_time), Some(end_time)) if end_time > start_time => end_time - start_time,
+            (None, Some(_), Some(_)) => return Err("end_time 必须晚于 start_time".to_string()),
+            (None, None, Some(_)) => return Err("end_time 只能与 start_time 一起使用".to_string()),
+            (None, _, None) => return Err("必须指定 duration 或 end_time".to_string()),
+        };
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err("duration 必须大于0".to_string());
+        }
+
+        let output = ClipOutput {
+            container: self.container.as_deref().map(OutputContainer::parse).transpose()?,
+            codec: self.codec.as_deref().map(CodecPreset::parse).transpose()?,
+            encoding: self.encoding.clone(),
+            package: self.package.as_deref().map(ClipPackage::parse).transpose()?,
+            overlay: self.overlay.clone(),
+            masks: self.masks.clone().unwrap_or_default(),
+            crop: self.crop.clone(),
+            width: self.width,
+            height: self.height,
+        };
+        output.validate()?;
+
+        Ok(ClipJobParams {
+            url: self.url.clone(),
+            source_id: self.source_id.clone(),
+            start,
+            start_time,
+            duration,
+            profile: self.profile.clone(),
+            output,
+        })
+    }
+}
+
+impl SpriteRequest {
+    /// 校验并转换为雪碧图参数
+    pub fn to_options(&self) -> Result<SpriteOptions, String> {
+        let mut options = SpriteOptions::default();
+
+        if let Some(duration) = self.duration
+            && (!duration.is_finite() || duration <= 0.0)
+        {
+            return Err("duration 必须大于0".to_string());
+        }
+        match (self.interval, self.count) {
+            (Some(_), Some(_)) => return Err("count 与 interval 不能同时指定".to_string()),
+            (Some(interval), None) => {
+                if !interval.is_finite() || interval <= 0.0 {
+                    return Err("interval 必须大于0".to_string());
+                }
+                let duration = self.duration.ok_or("按间隔取样时必须指定 duration")?;
+                options.count = (duration / interval).ceil() as usize;
+            }
+            (None, Some(count)) => options.count = count,
+            (None, None) => {}
+        }
+        if options.count == 0 || options.count > MAX_BATCH_FRAMES {
+            return Err(format!("缩略图数量必须在 1-{} 之间", MAX_BATCH_FRAMES));
+        }
+
+        if let Some(columns) = self.columns {
+            if columns == 0 || columns > 20 {
+                return Err("columns 必须在 1-20 之间".to_string());
+            }
+            options.columns = columns;
+        }
+        if let Some(width) = self.width {
+            if !(16..=640).contains(&width) {
+                return Err("width 必须在 16-640 之间".to_string());
+            }
+            options.thumb_width = width;
+        }
+
+        Ok(options)
+    }
+}
+
+/// 动图预览的最大时长（秒）
+pub const MAX_PREVIEW_DURATION: f64 = 30.0;
+
+impl PreviewRequest {
+    /// 校验并转换为动图参数
+    pub fn to_options(&self) -> Result<PreviewOptions, String> {
+        let start = self.start.unwrap_or(0.0);
+        if !start.is_finite() || start < 0.0 {
+            return Err("start 必须是非负数".to_string());
+        }
+        if !self.duration.is_finite() || self.duration <= 0.0 || self.duration > MAX_PREVIEW_DURATION {
+            return Err(format!("duration 必须在 0-{} 秒之间", MAX_PREVIEW_DURATION));
+        }
+
+        let mut options = PreviewOptions::default();
+        if let Some(format) = &self.format {
+            options.format = PreviewFormat::parse(format)?;
+        }
+        if let Some(fps) = self.fps {
+            if !(1..=30).contains(&fps) {
+                return Err("fps 必须在 1-30 之间".to_string());
+            }
+            options.fps = fps;
+        }
+        if let Some(width) = self.width {
+            if !(16..=1280).contains(&width) {
+                return Err("width 必须在 16-1280 之间".to_string());
+            }
+            options.width = width;
+        }
+
+        Ok(options)
+    }
+}
+
+impl AudioRequest {
+    /// 校验并转换为音频提取参数
+    pub fn to_options(&self) -> Result<AudioOptions, String> {
+        let start = self.start.unwrap_or(0.0);
+        if !start.is_finite() || start < 0.0 {
+            return Err("start 必须是非负数".to_string());
+        }
+        if !self.duration.is_finite() || self.duration <= 0.0 {
+            return Err("duration 必须大于0".to_string());
+        }
+
+        let options = AudioOptions {
+            format: self.format.as_deref().map(AudioFormat::parse).transpose()?.unwrap_or_default(),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            loudnorm: self.loudnorm.unwrap_or(false),
+            bitrate_kbps: self.bitrate,
+        };
+        options.validate()?;
+        Ok(options)
+    }
+}
+
+/// 单次拼接的最大段数
+pub const MAX_CONCAT_SEGMENTS: usize = 20;
+
+impl ConcatSegmentRequest {
+    /// 校验时间范围
+    pub fn validate(&self) -> Result<(), String> {
+        let start = self.start.unwrap_or(0.0);
+        if !start.is_finite() || start < 0.0 {
+            return Err("start 必须是非负数".to_string());
+        }
+        if !self.duration.is_finite() || self.duration <= 0.0 {
+            return Err("duration 必须大于0".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl ConcatRequest {
+    /// 校验并转换为拼接输出参数
+    pub fn to_options(&self) -> Result<ConcatOptions, String> {
+        if self.segments.is_empty() {
+            return Err("segments 不能为空".to_string());
+        }
+        if self.segments.len() > MAX_CONCAT_SEGMENTS {
+            return Err(format!("最多拼接 {} 段", MAX_CONCAT_SEGMENTS));
+        }
+
+        let mut options = ConcatOptions::default();
+        if let Some(width) = self.width {
+            options.width = width;
+        }
+        if let Some(height) = self.height {
+            options.height = height;
+        }
+        // H.264 yuv420p要求宽高为偶数
+        if !(16..=3840).contains(&options.width) || !(16..=2160).contains(&options.height)
+            || options.width % 2 != 0 || options.height % 2 != 0
+        {
+            return Err("width/height 必须是偶数，且不超过 3840x2160".to_string());
+        }
+        if let Some(fps) = self.fps {
+            if !(1..=60).contains(&fps) {
+                return Err("fps 必须在 1-60 之间".to_string());
+            }
+            options.fps = fps;
+        }
+        if let Some(container) = &self.container {
+            options.container = OutputContainer::parse(container)?;
+            if options.container == OutputContainer::Webm {
+                return Err("拼接输出为H.264/AAC，不支持 webm 容器".to_string());
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// 画面拼接的最大时长（秒）
+pub const MAX_MOSAIC_DURATION: f64 = 3600.0;
+
+impl MosaicRequest {
+    /// 校验参数并转换为画面拼接任务参数
+    pub fn to_job_params(&self) -> Result<MosaicJobParams, String> {
+        let layout = MosaicLayout::parse(&self.layout)?;
+        if self.inputs.is_empty() {
+            return Err("inputs 不能为空".to_string());
+        }
+        if self.inputs.len() > layout.capacity() {
+            return Err(format!("布局 {} 最多 {} 路输入", self.layout, layout.capacity()));
+        }
+        if !self.duration.is_finite() || self.duration <= 0.0 || self.duration > MAX_MOSAIC_DURATION {
+            return Err(format!("duration 必须在 0-{} 秒之间", MAX_MOSAIC_DURATION));
+        }
+
+        let width = self.width.unwrap_or(1280);
+        let height = self.height.unwrap_or(720);
+        // H.264 yuv420p要求宽高为偶数
+        if !(16..=3840).contains(&width) || !(16..=2160).contains(&height) || !width.is_multiple_of(2) || !height.is_multiple_of(2) {
+            return Err("width/height 必须是偶数，且不超过 3840x2160".to_string());
+        }
+
+        let inputs = self.inputs.iter().map(|input| MosaicJobInput {
+            url: input.url.clone(),
+            source_id: input.source_id.clone(),
+            profile: input.profile.clone(),
+            label: input.label.clone(),
+        }).collect();
+
+        Ok(MosaicJobParams {
+            inputs,
+            duration: self.duration,
+            options: MosaicOptions {
+                layout,
+                width,
+                height,
+                fps: 25,
+                timestamp: self.timestamp.unwrap_or(false),
+            },
+        })
+    }
+}
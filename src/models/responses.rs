@@ -1,10 +1,55 @@
 use serde::Serialize;
 
+use crate::services::{JobStatus, RecordedSegment, RecordingGap};
+
 #[derive(Serialize)]
 pub struct ClipResponse {
     pub video_url: String,
 }
 
+// 音频提取结果
+#[derive(Serialize)]
+pub struct AudioResponse {
+    pub audio_url: String,
+}
+
+// 动图预览结果
+#[derive(Serialize)]
+pub struct PreviewResponse {
+    pub preview_url: String,
+}
+
+// 雪碧图生成结果
+#[derive(Serialize)]
+pub struct SpriteResponse {
+    pub sprite_url: String,
+    pub vtt_url: String,
+    pub columns: u32,
+    pub rows: u32,
+    pub thumb_width: u32,
+    pub thumb_height: u32,
+    pub frames: usize,
+}
+
+// 异步任务提交结果
+#[derive(Serialize)]
+pub struct JobSubmitResponse {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub status_url: String,
+}
+
+// 录像查询结果
+#[derive(Serialize)]
+pub struct RecordingListResponse {
+    pub source_id: String,
+    pub from: f64,
+    pub to: f64,
+    pub recorded_seconds: f64, // 查询范围内有录像的总时长
+    pub segments: Vec<RecordedSegment>,
+    pub gaps: Vec<RecordingGap>,
+}
+
 // 并发请求统计结构体
 #[derive(Serialize)]
 pub struct ConcurrentStats {
@@ -0,0 +1,5 @@
+pub mod queue;
+pub mod worker;
+ 
+pub use queue::*;
+pub use worker::*;
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use serde::Serialize;
+use tokio::sync::{mpsc, watch, Mutex};
+use uuid::Uuid;
+
+use crate::services::source::redact_text;
+use crate::services::video::{ClipOutput, FfmpegProgress, MosaicOptions};
+use crate::utils::now_secs;
+
+/// 已结束任务在内存中的保留时间（秒）
+const FINISHED_JOB_TTL_SECS: u64 = 3600;
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    /// 任务是否已经结束（成功、失败或已取消）
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+/// 剪辑任务参数
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipJobParams {
+    /// 直接指定的拉流地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 视频源ID，执行时解析为带凭据的拉流地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
+    pub start: f64,
+    /// 从录像中剪辑的开始时间（Unix秒），指定时忽略 `start`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<f64>,
+    pub duration: f64,
+    pub profile: Option<String>,
+    /// 输出容器和编码
+    #[serde(flatten)]
+    pub output: ClipOutput,
+}
+
+/// 画面拼接任务的一路输入
+#[derive(Debug, Clone, Serialize)]
+pub struct MosaicJobInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 视频源ID，执行时解析为带凭据的拉流地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// 格内显示的名称，为空时使用视频源名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// 画面拼接任务参数
+#[derive(Debug, Clone, Serialize)]
+pub struct MosaicJobParams {
+    pub inputs: Vec<MosaicJobInput>,
+    pub duration: f64,
+    /// 布局、尺寸和时间叠加
+    #[serde(flatten)]
+    pub options: MosaicOptions,
+}
+
+/// 任务参数，`kind` 区分任务类型
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum JobParams {
+    Clip(Box<ClipJobParams>),
+    Mosaic(MosaicJobParams),
+}
+
+impl JobParams {
+    /// 输出时长，用于换算完成百分比
+    pub fn duration(&self) -> f64 {
+        match self {
+            JobParams::Clip(params) => params.duration,
+            JobParams::Mosaic(params) => params.duration,
+        }
+    }
+}
+
+impl From<ClipJobParams> for JobParams {
+    fn from(params: ClipJobParams) -> Self {
+        JobParams::Clip(Box::new(params))
+    }
+}
+
+impl From<MosaicJobParams> for JobParams {
+    fn from(params: MosaicJobParams) -> Self {
+        JobParams::Mosaic(params)
+    }
+}
+
+/// 任务信息快照，直接作为 `GET /api/jobs/{id}` 的响应体
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub status: JobStatus,
+    #[serde(flatten)]
+    pub params: JobParams,
+    /// 完成百分比（0-100）
+    pub percent: f64,
+    /// ffmpeg最近一次上报的进度
+    pub progress: Option<FfmpegProgress>,
+    pub filename: Option<String>,
+    pub video_url: Option<String>,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+/// 视频任务队列
+///
+/// 提交任务立即返回任务ID，由固定数量的工作协程从有界队列中取出执行。
+/// 每个任务的状态保存在 `watch` 通道中，既可以随时查询，也可以等待其结束。
+#[derive(Clone)]
+pub struct JobQueue {
+    jobs: Arc<RwLock<HashMap<String, watch::Sender<JobInfo>>>>,
+    sender: mpsc::Sender<String>,
+    receiver: Arc<Mutex<mpsc::Receiver<String>>>,
+}
+
+impl JobQueue {
+    /// 创建任务队列
+    ///
+    /// # Arguments
+    /// * `capacity` - 排队中任务的最大数量，超过后拒绝提交
+    pub fn new(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity);
+
+        Self {
+            jobs: Arc::new(RwLock::new(HashMap::new())),
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+
+    /// 提交剪辑或拼接任务，返回排队状态的任务信息
+    ///
+    /// 队列已满时返回错误
+    pub fn submit(&self, params: impl Into<JobParams>) -> Result<JobInfo, String> {
+        self.prune_finished();
+
+        let info = JobInfo {
+            id: Uuid::new_v4().to_string(),
+            status: JobStatus::Queued,
+            params: params.into(),
+            percent: 0.0,
+            progress: None,
+            filename: None,
+            video_url: None,
+            error: None,
+            created_at: now_secs(),
+            started_at: None,
+            finished_at: None,
+        };
+
+        let (tx, _) = watch::channel(info.clone());
+        self.jobs.write().unwrap().insert(info.id.clone(), tx);
+
+        if let Err(e) = self.sender.try_send(info.id.clone()) {
+            self.jobs.write().unwrap().remove(&info.id);
+            tracing::warn!("Job queue rejected job {}: {}", info.id, e);
+            return Err("任务队列已满，请稍后重试".to_string());
+        }
+
+        tracing::info!("Job {} queued", info.id);
+        Ok(info)
+    }
+
+    /// 查询任务当前状态
+    pub fn get(&self, id: &str) -> Option<JobInfo> {
+        self.jobs.read().unwrap()
+            .get(id)
+            .map(|tx| tx.borrow().clone())
+    }
+
+    /// 订阅任务状态变化
+    pub fn subscribe(&self, id: &str) -> Option<watch::Receiver<JobInfo>> {
+        self.jobs.read().unwrap()
+            .get(id)
+            .map(|tx| tx.subscribe())
+    }
+
+    /// 等待任务结束，返回最终状态
+    pub async fn wait(&self, id: &str) -> Option<JobInfo> {
+        let mut rx = self.subscribe(id)?;
+        let info = rx.wait_for(|job| job.status.is_finished()).await.ok()?;
+        Some(info.clone())
+    }
+
+    /// 修改任务状态并通知所有订阅者
+    pub fn update<F>(&self, id: &str, f: F)
+    where
+        F: FnOnce(&mut JobInfo),
+    {
+        if let Some(tx) = self.jobs.read().unwrap().get(id) {
+            tx.send_modify(f);
+        }
+    }
+
+    /// 修改尚未结束的任务，已结束的任务（例如已取消）保持不变
+    fn update_unfinished<F>(&self, id: &str, f: F)
+    where
+        F: FnOnce(&mut JobInfo),
+    {
+        self.update(id, |job| {
+            if !job.status.is_finished() {
+                f(job);
+            }
+        });
+    }
+
+    /// 标记任务开始执行
+    pub fn mark_running(&self, id: &str) {
+        self.update_unfinished(id, |job| {
+            job.status = JobStatus::Running;
+            job.started_at = Some(now_secs());
+        });
+    }
+
+    /// 更新任务进度，按输出时长换算完成百分比
+    pub fn update_progress(&self, id: &str, progress: &FfmpegProgress) {
+        self.update_unfinished(id, |job| {
+            let duration = job.params.duration();
+            if duration > 0.0 {
+                job.percent = (progress.out_time / duration * 100.0).clamp(0.0, 100.0);
+            }
+            job.progress = Some(progress.clone());
+        });
+    }
+
+    /// 标记任务执行成功
+    pub fn mark_succeeded(&self, id: &str, filename: &str) {
+        self.update_unfinished(id, |job| {
+            job.status = JobStatus::Succeeded;
+            job.percent = 100.0;
+            job.video_url = Some(format!("/clips/{}", filename));
+            job.filename = Some(filename.to_string());
+            job.finished_at = Some(now_secs());
+        });
+    }
+
+    /// 标记任务执行失败
+    pub fn mark_failed(&self, id: &str, error: &str) {
+        self.update_unfinished(id, |job| {
+            job.status = JobStatus::Failed;
+            // 错误信息可以通过任务接口读取，去掉其中拉流地址的凭据
+            job.error = Some(redact_text(error));
+            job.finished_at = Some(now_secs());
+        });
+    }
+
+    /// 取消任务
+    ///
+    /// 排队中的任务不会再被执行；运行中的任务由工作协程感知到状态变化后
+    /// 结束ffmpeg进程并删除残缺输出。已结束的任务无法取消。
+    pub fn cancel(&self, id: &str) -> Result<JobInfo, String> {
+        let jobs = self.jobs.read().unwrap();
+        let tx = jobs.get(id).ok_or_else(|| format!("任务不存在: {}", id))?;
+
+        let mut cancelled = false;
+        tx.send_if_modified(|job| {
+            if job.status.is_finished() {
+                return false;
+            }
+            job.status = JobStatus::Cancelled;
+            job.finished_at = Some(now_secs());
+            cancelled = true;
+            true
+        });
+
+        let info = tx.borrow().clone();
+        if cancelled {
+            tracing::info!("Job {} cancelled", id);
+            Ok(info)
+        } else {
+            Err(format!("任务已结束，无法取消: {:?}", info.status))
+        }
+    }
+
+    /// 该视频源未结束的剪辑任务中最早的录像开始时间，这之后的预录缓冲片段要保留到任务结束
+    pub fn earliest_pending_start(&self, source_id: &str) -> Option<f64> {
+        self.jobs.read().unwrap()
+            .values()
+            .filter_map(|tx| {
+                let job = tx.borrow();
+                match &job.params {
+                    JobParams::Clip(params)
+                        if !job.status.is_finished() && params.source_id.as_deref() == Some(source_id) =>
+                    {
+                        params.start_time
+                    }
+                    _ => None,
+                }
+            })
+            .min_by(f64::total_cmp)
+    }
+
+    /// 取出下一个待执行的任务ID，队列关闭时返回None
+    pub(crate) async fn next_job(&self) -> Option<String> {
+        self.receiver.lock().await.recv().await
+    }
+
+    /// 清理已结束且超过保留时间的任务
+    fn prune_finished(&self) {
+        let now = now_secs();
+        self.jobs.write().unwrap().retain(|_, tx| {
+            let job = tx.borrow();
+            match job.finished_at {
+                Some(finished_at) => now.saturating_sub(finished_at) < FINISHED_JOB_TTL_SECS,
+                None => true,
+            }
+        });
+    }
+}
+
+/// 任务取消守卫
+///
+/// 同步接口等待任务结束期间持有，客户端断开导致请求future被丢弃时自动取消任务
+pub struct JobCancelGuard {
+    queue: JobQueue,
+    id: Option<String>,
+}
+
+impl JobCancelGuard {
+    pub fn new(queue: &JobQueue, id: &str) -> Self {
+        Self {
+            queue: queue.clone(),
+            id: Some(id.to_string()),
+        }
+    }
+
+    /// 任务已正常结束，不再需要取消
+    pub fn disarm(&mut self) {
+        self.id = None;
+    }
+}
+
+impl Drop for JobCancelGuard {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take()
+            && self.queue.cancel(&id).is_ok()
+        {
+            tracing::info!("Client disconnected, job {} cancelled", id);
+        }
+    }
+}
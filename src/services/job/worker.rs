@@ -0,0 +1,234 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::models::AppState;
+use crate::services::source::redact_text;
+use crate::services::video::{MosaicInput, ProgressCallback};
+use super::{ClipJobParams, JobInfo, JobParams, JobStatus, MosaicJobParams};
+use crate::utils::{format_utc, now_secs_f64, send_feishu_notification};
+
+/// 剪辑包含当前时刻之后的画面时，结束时间之后再等待的秒数，让录像片段写入最后的画面
+const LIVE_TAIL_MARGIN: f64 = 3.0;
+
+/// 启动任务工作协程
+///
+/// # Arguments
+/// * `state` - 应用状态，工作协程通过它访问任务队列和视频服务
+/// * `workers` - 工作协程数量，即同时运行的ffmpeg进程上限
+pub fn spawn_job_workers(state: Arc<AppState>, workers: usize) {
+    for worker_id in 0..workers {
+        let state = state.clone();
+        tokio::spawn(async move {
+            tracing::info!("Job worker {} started", worker_id);
+            while let Some(job_id) = state.job_queue.next_job().await {
+                run_job(&state, &job_id).await;
+            }
+            tracing::info!("Job worker {} stopped", worker_id);
+        });
+    }
+}
+
+/// 执行单个任务，按任务类型分派
+async fn run_job(state: &AppState, job_id: &str) {
+    let (Some(job), Some(job_rx)) = (state.job_queue.get(job_id), state.job_queue.subscribe(job_id)) else {
+        tracing::warn!("Job {} disappeared before execution", job_id);
+        return;
+    };
+    if job.status == JobStatus::Cancelled {
+        tracing::info!("Job {} was cancelled before execution", job_id);
+        return;
+    }
+
+    match job.params {
+        JobParams::Clip(params) => run_clip_job(state, job_id, *params, job_rx).await,
+        JobParams::Mosaic(params) => run_mosaic_job(state, job_id, params, job_rx).await,
+    }
+}
+
+/// 标记任务开始执行，返回向任务队列上报进度的回调
+fn start_job(state: &AppState, job_id: &str) -> ProgressCallback {
+    state.job_queue.mark_running(job_id);
+    tracing::info!("Job {} running", job_id);
+
+    let queue = state.job_queue.clone();
+    let id = job_id.to_string();
+    Arc::new(move |progress| {
+        queue.update_progress(&id, progress);
+    })
+}
+
+/// 运行任务直到完成，任务被取消时返回None
+///
+/// 取消时丢弃任务future，ffmpeg进程组随之结束，残缺输出被删除
+async fn run_cancellable<F>(job_id: &str, mut job_rx: watch::Receiver<JobInfo>, task: F) -> Option<Result<String, String>>
+where
+    F: Future<Output = Result<String, String>>,
+{
+    tokio::select! {
+        result = task => Some(result),
+        _ = job_rx.wait_for(|job| job.status == JobStatus::Cancelled) => {
+            tracing::info!("Job {} cancelled while running, ffmpeg stopped", job_id);
+            None
+        }
+    }
+}
+
+/// 执行剪辑任务并更新任务状态
+async fn run_clip_job(state: &AppState, job_id: &str, params: ClipJobParams, job_rx: watch::Receiver<JobInfo>) {
+    let input = match state.sources.resolve(params.source_id.as_deref(), params.url.as_deref()) {
+        Ok(input) => input,
+        Err(e) => {
+            // 错误信息会写入日志并发送到飞书，去掉拉流地址中的凭据
+            let e = redact_text(&e);
+            state.job_queue.mark_failed(job_id, &e);
+            tracing::error!("Job {} failed: {}", job_id, e);
+            return;
+        }
+    };
+    // 请求指定的摄像头配置优先于视频源登记的配置
+    let profile = params.profile.as_deref().or(input.profile.as_deref());
+
+    // 视频源的遮挡在执行时读取，提交后修改的遮挡同样生效
+    let output = params.output.with_masks(&input.masks);
+
+    let start_label = match params.start_time {
+        Some(start_time) => format!("{} UTC", format_utc(start_time as u64)),
+        None => format!("{} 秒", params.start),
+    };
+
+    let on_progress = start_job(state, job_id);
+    let clip = async {
+        // 指定了开始时间时从录像或预录缓冲的片段剪辑，片段是原始编码，不使用摄像头配置
+        let archive = match (params.start_time, params.source_id.as_deref()) {
+            (Some(start_time), Some(source_id)) => {
+                let source = state.sources.get(source_id)
+                    .ok_or_else(|| format!("视频源不存在: {}", source_id))?;
+                let index = state.archive_for(&source);
+                // 剪辑包含当前时刻之后的画面时，等直播画面写入片段后再剪辑
+                let wait = start_time + params.duration + LIVE_TAIL_MARGIN - now_secs_f64();
+                if wait > 0.0 {
+                    tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+                }
+                index.refresh(source_id);
+                Some(index.select(source_id, start_time, params.duration)?)
+            }
+            _ => None,
+        };
+        match archive {
+            Some(selection) => {
+                state.video_service
+                    .clip_archive(
+                        &selection.segments,
+                        selection.offset,
+                        selection.start_time,
+                        params.duration,
+                        &output,
+                        Some(on_progress),
+                    )
+                    .await
+            }
+            None => {
+                state.video_service
+                    .clip_video(&input.url, params.start, params.duration, profile, &output, Some(on_progress))
+                    .await
+            }
+        }
+    };
+    let Some(result) = run_cancellable(job_id, job_rx, clip).await else {
+        return;
+    };
+
+    match result {
+        Ok(filename) => {
+            // 记录来源视频源，清理时按视频源的保留策略处理
+            if let Err(e) = state.clips.record(&filename, params.source_id.as_deref()) {
+                tracing::warn!("Failed to record clip {}: {}", filename, e);
+            }
+            state.job_queue.mark_succeeded(job_id, &filename);
+            tracing::info!("Job {} succeeded: {}", job_id, filename);
+
+            // 飞书通知：截视频成功
+            let msg = format!(
+                "【视频截取成功】\n视频源: {}\n起始: {}\n时长: {} 秒\n文件: {}",
+                input.label, start_label, params.duration, filename
+            );
+            send_feishu_notification(state, &msg).await;
+        }
+        Err(e) => {
+            // 错误信息会写入日志并发送到飞书，去掉拉流地址中的凭据
+            let e = redact_text(&e);
+            state.job_queue.mark_failed(job_id, &e);
+            tracing::error!("Job {} failed: {}", job_id, e);
+
+            // 飞书通知：截视频失败
+            let msg = format!(
+                "【视频截取失败】\n视频源: {}\n起始: {}\n时长: {} 秒\n错误: {}",
+                input.label, start_label, params.duration, e
+            );
+            send_feishu_notification(state, &msg).await;
+        }
+    }
+}
+
+/// 执行画面拼接任务并更新任务状态
+async fn run_mosaic_job(state: &AppState, job_id: &str, params: MosaicJobParams, job_rx: watch::Receiver<JobInfo>) {
+    // 凭据在执行时解析，任务信息中只保存视频源ID
+    let mut inputs = Vec::with_capacity(params.inputs.len());
+    for (i, input) in params.inputs.iter().enumerate() {
+        match state.sources.resolve(input.source_id.as_deref(), input.url.as_deref()) {
+            Ok(resolved) => inputs.push(MosaicInput {
+                url: resolved.url,
+                profile: input.profile.clone().or(resolved.profile),
+                label: input.label.clone().or_else(|| input.source_id.as_ref().map(|_| resolved.label)),
+                masks: resolved.masks,
+            }),
+            Err(e) => {
+                let e = format!("第 {} 路输入: {}", i + 1, e);
+                state.job_queue.mark_failed(job_id, &e);
+                tracing::error!("Job {} failed: {}", job_id, e);
+                return;
+            }
+        }
+    }
+
+    let on_progress = start_job(state, job_id);
+    let mosaic = state.video_service
+        .compose_mosaic(&inputs, params.duration, &params.options, Some(on_progress));
+    let Some(result) = run_cancellable(job_id, job_rx, mosaic).await else {
+        return;
+    };
+
+    match result {
+        Ok(filename) => {
+            // 多路输入时按第一个视频源的保留策略清理
+            let source_id = params.inputs.iter().find_map(|input| input.source_id.as_deref());
+            if let Some(source_id) = source_id
+                && let Err(e) = state.clips.record(&filename, Some(source_id))
+            {
+                tracing::warn!("Failed to record clip {}: {}", filename, e);
+            }
+            state.job_queue.mark_succeeded(job_id, &filename);
+            tracing::info!("Job {} succeeded: {}", job_id, filename);
+
+            let msg = format!(
+                "【画面拼接成功】\n画面数: {}\n时长: {} 秒\n文件: {}",
+                inputs.len(), params.duration, filename
+            );
+            send_feishu_notification(state, &msg).await;
+        }
+        Err(e) => {
+            // 错误信息会写入日志并发送到飞书，去掉拉流地址中的凭据
+            let e = redact_text(&e);
+            state.job_queue.mark_failed(job_id, &e);
+            tracing::error!("Job {} failed: {}", job_id, e);
+
+            let msg = format!(
+                "【画面拼接失败】\n画面数: {}\n时长: {} 秒\n错误: {}",
+                inputs.len(), params.duration, e
+            );
+            send_feishu_notification(state, &msg).await;
+        }
+    }
+}
@@ -1,5 +1,13 @@
 pub mod video;
 pub mod notification;
+pub mod job;
+pub mod source;
+pub mod recording;
+pub mod retention;
  
 pub use video::*;
-pub use notification::*; 
\ No newline at end of file
+pub use notification::*;
+pub use job::*;
+pub use source::*;
+pub use recording::*;
+pub use retention::*;
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::UNIX_EPOCH;
+use serde::Serialize;
+
+use crate::utils::utc_to_unix;
+
+/// 一个录像片段
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedSegment {
+    /// 视频源录像目录下的文件名
+    pub file: String,
+    /// 开始时间（Unix秒），由文件名确定
+    pub start: f64,
+    /// 结束时间（Unix秒），取文件最后写入时间，正在录制的片段会继续增长
+    pub end: f64,
+    pub size: u64,
+}
+
+impl RecordedSegment {
+    /// 从录像文件读取片段信息，文件名不是录像片段时返回None
+    fn read(path: &Path) -> Option<Self> {
+        let file = path.file_name()?.to_str()?.to_string();
+        let start = parse_segment_name(&file)? as f64;
+        let metadata = std::fs::metadata(path).ok()?;
+        let end = metadata.modified().ok()?
+            .duration_since(UNIX_EPOCH).ok()?
+            .as_secs_f64();
+        Some(Self { file, start, end: end.max(start), size: metadata.len() })
+    }
+}
+
+/// 解析录像片段文件名（UTC时间），例如 `20261017T142300Z.ts`
+fn parse_segment_name(name: &str) -> Option<i64> {
+    let stem = name.strip_suffix("Z.ts")?;
+    let (date, time) = stem.split_once('T')?;
+    if date.len() != 8 || time.len() != 6 || !stem.chars().all(|c| c.is_ascii_digit() || c == 'T') {
+        return None;
+    }
+    let field = |s: &str| s.parse::<u32>().ok();
+    Some(utc_to_unix(
+        date[0..4].parse().ok()?,
+        field(&date[4..6])?,
+        field(&date[6..8])?,
+        field(&time[0..2])?,
+        field(&time[2..4])?,
+        field(&time[4..6])?,
+    ))
+}
+
+/// 片段之间小于该值（秒）的空隙不算录像中断：文件名只精确到秒，结束时间取文件最后写入时间
+const GAP_TOLERANCE: f64 = 2.0;
+
+/// 录像中断的时间段
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingGap {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// 找出 `[from, to)` 内没有片段覆盖的时间段，`segments` 须按时间排序
+pub fn find_gaps(segments: &[RecordedSegment], from: f64, to: f64) -> Vec<RecordingGap> {
+    let mut gaps = Vec::new();
+    let mut covered = from;
+    for segment in segments {
+        if segment.start - covered > GAP_TOLERANCE {
+            gaps.push(RecordingGap { start: covered, end: segment.start.min(to) });
+        }
+        covered = covered.max(segment.end);
+    }
+    if to - covered > GAP_TOLERANCE {
+        gaps.push(RecordingGap { start: covered, end: to });
+    }
+    gaps
+}
+
+/// 为剪辑选出的录像片段
+pub struct ArchiveSelection {
+    /// 片段的绝对路径，按时间排序
+    pub segments: Vec<PathBuf>,
+    /// 剪辑起点相对第一个片段开头的偏移（秒）
+    pub offset: f64,
+    /// 剪辑第一帧的录制时间（Unix秒），请求的开始时间没有录像时晚于请求的时间
+    pub start_time: f64,
+}
+
+/// 录像片段索引
+///
+/// 每个视频源一个目录，片段按开始时间排序。启动时扫描录像目录，
+/// 之后由录像任务定期刷新正在录制的视频源。
+#[derive(Clone)]
+pub struct RecordingIndex {
+    dir: PathBuf,
+    segments: Arc<RwLock<HashMap<String, Vec<RecordedSegment>>>>,
+}
+
+impl RecordingIndex {
+    /// 创建空索引，不扫描目录
+    pub fn new(dir: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+            segments: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 扫描录像目录下所有视频源的片段
+    pub fn load(dir: &str) -> Result<Self, String> {
+        let index = Self::new(dir);
+        if !Path::new(dir).exists() {
+            return Ok(index);
+        }
+
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| format!("读取录像目录失败 {}: {}", dir, e))?;
+        let mut total = 0;
+        for entry in entries.flatten() {
+            if entry.path().is_dir()
+                && let Some(id) = entry.file_name().to_str()
+            {
+                index.refresh(id);
+                total += index.segments.read().unwrap().get(id).map_or(0, Vec::len);
+            }
+        }
+
+        tracing::info!("Loaded {} recorded segments from {}", total, dir);
+        Ok(index)
+    }
+
+    /// 视频源的录像目录
+    pub fn source_dir(&self, source_id: &str) -> PathBuf {
+        self.dir.join(source_id)
+    }
+
+    /// 重新读取视频源的录像目录
+    ///
+    /// 只读取新出现的文件和最后一个（可能仍在写入的）片段，已完成的片段不再读取
+    pub fn refresh(&self, source_id: &str) {
+        let dir = self.source_dir(source_id);
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return;
+        };
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| parse_segment_name(name).is_some())
+            .collect();
+        names.sort();
+
+        let mut index = self.segments.write().unwrap();
+        let segments = index.entry(source_id.to_string()).or_default();
+        // 文件名按时间排序，最后一个已知片段之前的都已完成
+        let known = segments.len().saturating_sub(1);
+        let last_known = segments.get(known).map(|s| s.file.clone());
+        segments.truncate(known);
+        segments.extend(
+            names.iter()
+                .filter(|name| last_known.as_ref().is_none_or(|last| *name >= last))
+                .filter_map(|name| RecordedSegment::read(&dir.join(name))),
+        );
+    }
+
+    /// 索引中的视频源ID
+    pub fn source_ids(&self) -> Vec<String> {
+        self.segments.read().unwrap().keys().cloned().collect()
+    }
+
+    /// 删除结束时间早于 `before` 的片段文件，返回删除的数量
+    pub fn prune(&self, source_id: &str, before: f64) -> usize {
+        self.remove_where(source_id, |segment| segment.end < before).len()
+    }
+
+    /// 删除指定文件名的片段，返回删除的片段
+    pub fn remove(&self, source_id: &str, files: &[String]) -> Vec<RecordedSegment> {
+        self.remove_where(source_id, |segment| files.contains(&segment.file))
+    }
+
+    /// 删除满足条件的片段文件并移出索引，删除失败的片段保留在索引中
+    fn remove_where(&self, source_id: &str, matches: impl Fn(&RecordedSegment) -> bool) -> Vec<RecordedSegment> {
+        let dir = self.source_dir(source_id);
+        let mut index = self.segments.write().unwrap();
+        let Some(segments) = index.get_mut(source_id) else {
+            return Vec::new();
+        };
+
+        let mut removed = Vec::new();
+        segments.retain(|segment| {
+            if !matches(segment) {
+                return true;
+            }
+            match std::fs::remove_file(dir.join(&segment.file)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    tracing::warn!("Failed to remove segment {}/{}: {}", source_id, segment.file, e);
+                    return true;
+                }
+            }
+            removed.push(segment.clone());
+            false
+        });
+        removed
+    }
+
+    /// 与 `[from, to)` 有重叠的片段，按时间排序
+    pub fn segments(&self, source_id: &str, from: f64, to: f64) -> Vec<RecordedSegment> {
+        self.segments.read().unwrap()
+            .get(source_id)
+            .map(|segments| {
+                segments.iter()
+                    .filter(|s| s.end > from && s.start < to)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 选出覆盖 `[start, start + duration)` 的片段
+    ///
+    /// 中间没有录像的时间会被跳过，所以输出可能比请求的时长短
+    pub fn select(&self, source_id: &str, start: f64, duration: f64) -> Result<ArchiveSelection, String> {
+        let segments = self.segments(source_id, start, start + duration);
+        let Some(first) = segments.first() else {
+            return Err("该时间段没有录像".to_string());
+        };
+        let offset = (start - first.start).max(0.0);
+
+        let dir = self.source_dir(source_id).canonicalize()
+            .map_err(|e| format!("录像目录不存在: {}", e))?;
+        Ok(ArchiveSelection {
+            segments: segments.iter().map(|segment| dir.join(&segment.file)).collect(),
+            offset,
+            start_time: first.start + offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, end: f64) -> RecordedSegment {
+        RecordedSegment { file: String::new(), start, end, size: 0 }
+    }
+
+    #[test]
+    fn segment_names() {
+        assert_eq!(parse_segment_name("20261017T142300Z.ts"), Some(1_792_246_980));
+        assert_eq!(parse_segment_name("19700101T000000Z.ts"), Some(0));
+        assert_eq!(parse_segment_name("20261017T142300Z.mp4"), None);
+        assert_eq!(parse_segment_name("20261017T1423Z.ts"), None);
+        assert_eq!(parse_segment_name("2026-10-17T142300Z.ts"), None);
+        assert_eq!(parse_segment_name("20261017T14230ZZ.ts"), None);
+        assert_eq!(parse_segment_name("2026101７T142300Z.ts"), None);
+    }
+
+    #[test]
+    fn gaps_between_segments() {
+        let segments = [segment(100.0, 160.0), segment(161.0, 220.0), segment(300.0, 360.0)];
+
+        // 小于容差的空隙不算中断
+        let gaps = find_gaps(&segments, 100.0, 360.0);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!((gaps[0].start, gaps[0].end), (220.0, 300.0));
+
+        // 查询范围两端没有录像
+        let gaps = find_gaps(&segments, 0.0, 500.0);
+        let gaps: Vec<(f64, f64)> = gaps.iter().map(|gap| (gap.start, gap.end)).collect();
+        assert_eq!(gaps, vec![(0.0, 100.0), (220.0, 300.0), (360.0, 500.0)]);
+
+        // 空隙截断在查询范围内
+        let gaps = find_gaps(&segments, 130.0, 250.0);
+        let gaps: Vec<(f64, f64)> = gaps.iter().map(|gap| (gap.start, gap.end)).collect();
+        assert_eq!(gaps, vec![(220.0, 250.0)]);
+
+        let gaps = find_gaps(&[], 0.0, 60.0);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!((gaps[0].start, gaps[0].end), (0.0, 60.0));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod index;
+pub mod recorder;
+ 
+pub use index::*;
+pub use recorder::*;
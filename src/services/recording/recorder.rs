@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::models::AppState;
+use crate::services::source::Source;
+use crate::utils::now_secs_f64;
+
+/// 录像任务与视频源目录的同步间隔，也是索引刷新间隔
+const SYNC_INTERVAL: Duration = Duration::from_secs(5);
+/// ffmpeg退出后重新连接的等待时间
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+/// 预录缓冲的片段时长（秒），实际在其后的第一个关键帧处切分
+const BUFFER_SEGMENT_SECS: u64 = 2;
+/// 预录缓冲清理时多保留的时间（秒），避免正在剪辑的片段被删除
+const BUFFER_PRUNE_MARGIN: f64 = 30.0;
+
+/// 预录缓冲的清理时间点，结束时间早于它的片段被删除
+///
+/// 片段至少保留 `buffer_seconds` 加上余量，还没执行完的剪辑任务需要的片段（`pending_start` 之后）一直保留
+fn buffer_prune_cutoff(now: f64, buffer_seconds: u32, pending_start: Option<f64>) -> f64 {
+    let cutoff = now - buffer_seconds as f64 - BUFFER_PRUNE_MARGIN;
+    pending_start.map_or(cutoff, |start| cutoff.min(start))
+}
+
+/// 正在运行的录像任务
+struct RunningRecorder {
+    /// 启动时视频源的更新时间，视频源被修改后重启录像
+    updated_at: u64,
+    handle: JoinHandle<()>,
+}
+
+/// 启动录像管理协程
+///
+/// 定期对照视频源目录：`record` 为true的视频源持续录像，否则设置了 `buffer_seconds` 的视频源
+/// 录制到预录缓冲目录；关闭录像、修改或删除的视频源停止录像。每轮刷新片段索引，
+/// 并删除超出 `buffer_seconds` 且没有剪辑任务等待使用的缓冲片段
+///
+/// # Arguments
+/// * `state` - 应用状态，通过它读取视频源并更新录像和缓冲索引
+/// * `segment_secs` - 录像片段时长（秒）
+pub fn spawn_recorders(state: Arc<AppState>, segment_secs: u64) {
+    tokio::spawn(async move {
+        let mut running: HashMap<String, RunningRecorder> = HashMap::new();
+        let mut ticker = tokio::time::interval(SYNC_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let sources: HashMap<String, Source> = state.sources.list(None)
+                .into_iter()
+                .filter(|source| source.record || source.buffer_seconds.is_some())
+                .map(|source| (source.id.clone(), source))
+                .collect();
+
+            running.retain(|id, recorder| {
+                let keep = sources.get(id).is_some_and(|source| source.updated_at == recorder.updated_at);
+                if !keep {
+                    // 丢弃录像future时ffmpeg进程组随之结束
+                    recorder.handle.abort();
+                    state.recordings.refresh(id);
+                    state.buffers.refresh(id);
+                    tracing::info!("Recorder for source {} stopped", id);
+                }
+                keep
+            });
+
+            let now = now_secs_f64();
+            for (id, source) in &sources {
+                if !running.contains_key(id) {
+                    tracing::info!("Recorder for source {} started", id);
+                    let handle = tokio::spawn(record_source(state.clone(), source.clone(), segment_secs));
+                    running.insert(id.clone(), RunningRecorder { updated_at: source.updated_at, handle });
+                }
+
+                if source.record {
+                    state.recordings.refresh(id);
+                } else if let Some(seconds) = source.buffer_seconds {
+                    state.buffers.refresh(id);
+                    let pending_start = state.job_queue.earliest_pending_start(id);
+                    state.buffers.prune(id, buffer_prune_cutoff(now, seconds, pending_start));
+                }
+            }
+
+            // 不再缓冲的视频源（改为持续录像、关闭缓冲或删除）清空缓冲
+            for id in state.buffers.source_ids() {
+                let buffered = sources.get(&id).is_some_and(|source| !source.record);
+                if !buffered {
+                    // 还没执行完的剪辑任务需要的片段保留到任务结束
+                    let before = state.job_queue.earliest_pending_start(&id).unwrap_or(f64::INFINITY);
+                    let removed = state.buffers.prune(&id, before);
+                    if removed > 0 {
+                        tracing::info!("Cleared {} buffered segments of source {}", removed, id);
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// 持续录制一个视频源到录像目录或预录缓冲目录，ffmpeg退出（断流、摄像头重启）后等待片刻重新连接
+async fn record_source(state: Arc<AppState>, source: Source, segment_secs: u64) {
+    let (dir, segment_secs) = if source.record {
+        (state.recordings.source_dir(&source.id), segment_secs)
+    } else {
+        (state.buffers.source_dir(&source.id), BUFFER_SEGMENT_SECS)
+    };
+    loop {
+        let result = match source.stream_url() {
+            Ok(url) => {
+                state.video_service
+                    .record_segments(&url, source.profile.as_deref(), &dir, segment_secs)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => tracing::warn!("Recorder for source {} exited, reconnecting", source.id),
+            Err(e) => tracing::error!("Recorder for source {} failed, reconnecting: {}", source.id, e),
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{ClipJobParams, JobQueue};
+
+    fn clip_params(source_id: &str, start_time: f64, duration: f64) -> ClipJobParams {
+        ClipJobParams {
+            url: None,
+            source_id: Some(source_id.to_string()),
+            start: 0.0,
+            start_time: Some(start_time),
+            duration,
+            profile: None,
+            output: Default::default(),
+        }
+    }
+
+    #[test]
+    fn pending_clip_keeps_pre_event_buffer() {
+        // buffer_seconds=10，在T时刻提交 start=-10、duration=60 的剪辑
+        let t = 1_000_000.0;
+        let start_time = t - 10.0;
+        let queue = JobQueue::new(8);
+        let job = queue.submit(clip_params("gate", start_time, 60.0)).unwrap();
+        queue.submit(clip_params("other", t - 500.0, 10.0)).unwrap();
+
+        // 任务等待直播画面写入片段，又在队列中排了一段时间，执行时所需的片段仍然保留
+        for now in [t, t + 53.0, t + 300.0] {
+            let pending = queue.earliest_pending_start("gate");
+            assert_eq!(pending, Some(start_time));
+            assert!(buffer_prune_cutoff(now, 10, pending) <= start_time);
+        }
+
+        // 任务结束后恢复按 buffer_seconds 清理
+        queue.mark_failed(&job.id, "done");
+        assert_eq!(queue.earliest_pending_start("gate"), None);
+        assert_eq!(buffer_prune_cutoff(t + 300.0, 10, None), t + 300.0 - 10.0 - BUFFER_PRUNE_MARGIN);
+    }
+}
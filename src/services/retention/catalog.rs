@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::now_secs;
+
+/// 剪辑文件的元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipMeta {
+    /// clips目录下的文件名，打包输出为子目录名
+    pub name: String,
+    /// 剪辑来自的视频源，按视频源的保留策略清理
+    #[serde(default)]
+    pub source_id: Option<String>,
+    pub created_at: u64,
+    /// 固定（法律保全）的剪辑不会被自动清理
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<ClipPin>,
+}
+
+/// 剪辑的固定信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipPin {
+    pub pinned_at: u64,
+    /// 保全到期时间（Unix秒），到期后按保留策略正常清理，为空表示一直保留
+    #[serde(default)]
+    pub until: Option<u64>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl ClipMeta {
+    /// 在 `now` 时刻是否处于固定状态
+    pub fn is_pinned(&self, now: f64) -> bool {
+        self.pin.as_ref().is_some_and(|pin| pin.until.is_none_or(|until| until as f64 > now))
+    }
+}
+
+/// 剪辑元数据目录
+///
+/// 只记录需要额外信息的剪辑，没有记录的剪辑按默认策略清理。
+/// 保存在JSON文件中，每次修改后整体写回（先写临时文件再重命名）。
+#[derive(Clone)]
+pub struct ClipCatalog {
+    path: PathBuf,
+    clips: Arc<RwLock<BTreeMap<String, ClipMeta>>>,
+}
+
+/// 输出文件名对应的clips目录顶层条目，打包输出 `{id}/index.m3u8` 对应子目录 `{id}`
+pub fn clip_entry_name(filename: &str) -> &str {
+    filename.split('/').next().unwrap_or(filename)
+}
+
+impl ClipCatalog {
+    /// 创建空目录，不读取文件，首次修改时写入该路径
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+            clips: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+
+    /// 从文件加载剪辑元数据，文件不存在时创建空目录
+    pub fn load(path: &str) -> Result<Self, String> {
+        let catalog = Self::new(path);
+        let clips = if Path::new(path).exists() {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| format!("读取剪辑元数据文件失败 {}: {}", path, e))?;
+            let list: Vec<ClipMeta> = serde_json::from_str(&content)
+                .map_err(|e| format!("解析剪辑元数据文件失败 {}: {}", path, e))?;
+            list.into_iter().map(|c| (c.name.clone(), c)).collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        tracing::info!("Loaded {} clip records from {}", clips.len(), path);
+
+        *catalog.clips.write().unwrap() = clips;
+        Ok(catalog)
+    }
+
+    /// 按名称查找剪辑元数据
+    pub fn get(&self, name: &str) -> Option<ClipMeta> {
+        self.clips.read().unwrap().get(name).cloned()
+    }
+
+    /// 记录新生成的剪辑，`filename` 为剪辑接口返回的文件名
+    pub fn record(&self, filename: &str, source_id: Option<&str>) -> Result<(), String> {
+        let name = clip_entry_name(filename).to_string();
+        let mut clips = self.clips.write().unwrap();
+        clips.insert(name.clone(), ClipMeta {
+            name,
+            source_id: source_id.map(str::to_string),
+            created_at: now_secs(),
+            pin: None,
+        });
+        self.persist(&clips)
+    }
+
+    /// 固定剪辑，已固定时更新到期时间和原因，返回更新后的元数据
+    ///
+    /// 没有记录的剪辑（例如音频、预览）在这里补一条记录
+    pub fn pin(&self, name: &str, until: Option<u64>, reason: Option<String>) -> Result<ClipMeta, String> {
+        let mut clips = self.clips.write().unwrap();
+        let now = now_secs();
+        let clip = clips.entry(name.to_string()).or_insert_with(|| ClipMeta {
+            name: name.to_string(),
+            source_id: None,
+            created_at: now,
+            pin: None,
+        });
+        clip.pin = Some(ClipPin { pinned_at: now, until, reason });
+        let clip = clip.clone();
+        self.persist(&clips)?;
+
+        tracing::info!("Clip {} pinned until {:?}", name, until);
+        Ok(clip)
+    }
+
+    /// 取消固定，返回更新后的元数据，没有记录时返回None
+    pub fn unpin(&self, name: &str) -> Result<Option<ClipMeta>, String> {
+        let mut clips = self.clips.write().unwrap();
+        let Some(clip) = clips.get_mut(name) else {
+            return Ok(None);
+        };
+        clip.pin = None;
+        let clip = clip.clone();
+        self.persist(&clips)?;
+
+        tracing::info!("Clip {} unpinned", name);
+        Ok(Some(clip))
+    }
+
+    /// 只保留 `keep` 返回true的记录，有记录被删除时写回文件
+    pub fn retain(&self, mut keep: impl FnMut(&ClipMeta) -> bool) -> Result<(), String> {
+        let mut clips = self.clips.write().unwrap();
+        let before = clips.len();
+        clips.retain(|_, clip| keep(clip));
+        if clips.len() == before {
+            return Ok(());
+        }
+        self.persist(&clips)
+    }
+
+    /// 写回文件
+    fn persist(&self, clips: &BTreeMap<String, ClipMeta>) -> Result<(), String> {
+        let list: Vec<&ClipMeta> = clips.values().collect();
+        let content = serde_json::to_string_pretty(&list)
+            .map_err(|e| format!("序列化剪辑元数据失败: {}", e))?;
+
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("创建剪辑元数据目录失败: {}", e))?;
+        }
+
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)
+            .map_err(|e| format!("写入剪辑元数据文件失败: {}", e))?;
+        std::fs::rename(&tmp, &self.path)
+            .map_err(|e| format!("保存剪辑元数据文件失败: {}", e))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(pin: Option<ClipPin>) -> ClipMeta {
+        ClipMeta { name: "clip.mp4".to_string(), source_id: None, created_at: 0, pin }
+    }
+
+    fn pin(until: Option<u64>) -> Option<ClipPin> {
+        Some(ClipPin { pinned_at: 0, until, reason: None })
+    }
+
+    #[test]
+    fn pin_expiry() {
+        let now = 1_000.0;
+        assert!(!clip(None).is_pinned(now));
+        assert!(clip(pin(None)).is_pinned(now));
+        assert!(clip(pin(Some(1_001))).is_pinned(now));
+        // 到期时刻起不再固定
+        assert!(!clip(pin(Some(1_000))).is_pinned(now));
+        assert!(!clip(pin(Some(999))).is_pinned(now));
+    }
+}
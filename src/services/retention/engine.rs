@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::models::AppState;
+use crate::services::source::Source;
+use crate::utils::now_secs_f64;
+use super::{RetainedItem, RetentionPolicy, RetentionRules};
+
+/// 最近这段时间（秒）内修改过的剪辑可能仍在写入，不会被删除
+const ACTIVE_GRACE_SECS: f64 = 120.0;
+
+/// clips目录下的一个条目（文件或打包子目录）
+struct ClipEntry {
+    name: String,
+    path: PathBuf,
+    item: RetainedItem,
+}
+
+/// 启动保留策略清理协程
+///
+/// 每隔 `interval_seconds` 按策略清理clips目录下的剪辑和各视频源的录像，并记录删除的文件。
+/// 预录缓冲由录像管理协程按 `buffer_seconds` 清理，不在这里处理
+///
+/// # Arguments
+/// * `state` - 应用状态，通过它读取视频源、剪辑元数据和录像索引
+/// * `rules` - 保留策略
+/// * `clips_dir` - 剪辑输出目录
+pub fn spawn_retention(state: Arc<AppState>, rules: RetentionRules, clips_dir: PathBuf) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(rules.interval_seconds));
+        loop {
+            ticker.tick().await;
+
+            let sources: HashMap<String, Source> = state.sources.list(None)
+                .into_iter()
+                .map(|source| (source.id.clone(), source))
+                .collect();
+            let now = now_secs_f64();
+            if let Err(e) = enforce_clips(&state, &rules, &clips_dir, &sources, now) {
+                tracing::warn!("Clip retention failed: {}", e);
+            }
+            enforce_recordings(&state, &rules, &sources, now);
+        }
+    });
+}
+
+/// 清理剪辑：有覆盖策略的视频源的剪辑单独计算，其余剪辑共用默认策略，固定的剪辑跳过
+fn enforce_clips(
+    state: &AppState,
+    rules: &RetentionRules,
+    clips_dir: &Path,
+    sources: &HashMap<String, Source>,
+    now: f64,
+) -> Result<(), String> {
+    let entries = std::fs::read_dir(clips_dir)
+        .map_err(|e| format!("Failed to read clips dir: {}", e))?;
+
+    // None为共用默认策略的剪辑
+    let mut groups: BTreeMap<Option<String>, Vec<ClipEntry>> = BTreeMap::new();
+    for entry in entries.flatten() {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        let modified = metadata.modified().ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(now, |d| d.as_secs_f64());
+        let size = if metadata.is_dir() { dir_size(&path) } else { metadata.len() };
+
+        // 固定的剪辑不删除，也不计入总量和数量
+        let clip = state.clips.get(&name);
+        if clip.as_ref().is_some_and(|clip| clip.is_pinned(now)) {
+            continue;
+        }
+        let group = clip
+            .and_then(|clip| clip.source_id)
+            .filter(|id| rules.clip_policy(id, sources.get(id)).is_some());
+        groups.entry(group).or_default().push(ClipEntry {
+            name,
+            path,
+            item: RetainedItem { modified, size, protected: modified > now - ACTIVE_GRACE_SECS },
+        });
+    }
+
+    let mut removed = HashSet::new();
+    for (group, mut entries) in groups {
+        let policy = match &group {
+            Some(id) => rules.clip_policy(id, sources.get(id)).unwrap_or_else(|| rules.clips.clone()),
+            None => rules.clips.clone(),
+        };
+        entries.sort_by(|a, b| a.item.modified.total_cmp(&b.item.modified));
+        let items: Vec<RetainedItem> = entries.iter().map(|entry| entry.item).collect();
+        for i in policy.select_expired(&items, now) {
+            let entry = &entries[i];
+            let result = if entry.path.is_dir() {
+                std::fs::remove_dir_all(&entry.path)
+            } else {
+                std::fs::remove_file(&entry.path)
+            };
+            match result {
+                Ok(()) => {
+                    tracing::info!(
+                        "Retention removed clip {} ({} bytes, source: {})",
+                        entry.name,
+                        entry.item.size,
+                        group.as_deref().unwrap_or("-")
+                    );
+                    removed.insert(entry.name.clone());
+                }
+                Err(e) => tracing::warn!("Failed to remove clip {}: {}", entry.name, e),
+            }
+        }
+    }
+
+    // 去掉已删除剪辑（包括被手动删除的）的元数据，扫描之后新生成的剪辑的记录保留
+    state.clips.retain(|clip| !removed.contains(&clip.name) && clips_dir.join(&clip.name).exists())
+}
+
+/// 清理各视频源的录像，最新的片段可能仍在录制，不会被删除
+fn enforce_recordings(state: &AppState, rules: &RetentionRules, sources: &HashMap<String, Source>, now: f64) {
+    for id in state.recordings.source_ids() {
+        let policy: RetentionPolicy = rules.recording_policy(&id, sources.get(&id));
+        if policy.is_unlimited() {
+            continue;
+        }
+
+        let segments = state.recordings.segments(&id, f64::NEG_INFINITY, f64::INFINITY);
+        let items: Vec<RetainedItem> = segments.iter()
+            .enumerate()
+            .map(|(i, segment)| RetainedItem {
+                modified: segment.end,
+                size: segment.size,
+                protected: i + 1 == segments.len(),
+            })
+            .collect();
+        let files: Vec<String> = policy.select_expired(&items, now)
+            .into_iter()
+            .map(|i| segments[i].file.clone())
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+
+        let removed = state.recordings.remove(&id, &files);
+        if let (Some(first), Some(last)) = (removed.first(), removed.last()) {
+            tracing::info!(
+                "Retention removed {} recorded segments of source {} ({} bytes, {} to {})",
+                removed.len(),
+                id,
+                removed.iter().map(|segment| segment.size).sum::<u64>(),
+                first.file,
+                last.file
+            );
+        }
+    }
+}
+
+/// 目录下所有文件的总大小
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
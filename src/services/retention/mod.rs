@@ -0,0 +1,7 @@
+pub mod catalog;
+pub mod engine;
+pub mod policy;
+ 
+pub use catalog::*;
+pub use engine::*;
+pub use policy::*;
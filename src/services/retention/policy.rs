@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::services::source::Source;
+
+/// 一组文件的保留策略，未设置的限制不生效
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// 最长保留时间（小时），按文件最后修改时间计算
+    #[serde(default)]
+    pub max_age_hours: Option<f64>,
+    /// 总大小上限（MB），超出时从最旧的开始删除
+    #[serde(default)]
+    pub max_total_mb: Option<u64>,
+    /// 数量上限，超出时从最旧的开始删除
+    #[serde(default)]
+    pub max_count: Option<usize>,
+}
+
+/// 参与保留策略计算的文件
+#[derive(Debug, Clone, Copy)]
+pub struct RetainedItem {
+    /// 最后修改时间（Unix秒）
+    pub modified: f64,
+    pub size: u64,
+    /// 受保护的文件（例如正在写入）计入总量和数量，但不会被删除
+    pub protected: bool,
+}
+
+/// 两个限制都设置时取较宽松的值
+fn lenient<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b > a { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+impl RetentionPolicy {
+    fn validate(&self, scope: &str) -> Result<(), String> {
+        if let Some(hours) = self.max_age_hours
+            && (!hours.is_finite() || hours <= 0.0)
+        {
+            return Err(format!("{} 的 max_age_hours 必须大于0", scope));
+        }
+        if self.max_total_mb == Some(0) {
+            return Err(format!("{} 的 max_total_mb 必须大于0", scope));
+        }
+        if self.max_count == Some(0) {
+            return Err(format!("{} 的 max_count 必须大于0", scope));
+        }
+        Ok(())
+    }
+
+    /// 是否没有任何限制
+    pub fn is_unlimited(&self) -> bool {
+        self.max_age_hours.is_none() && self.max_total_mb.is_none() && self.max_count.is_none()
+    }
+
+    /// 用 `other` 中设置了的限制覆盖本策略
+    fn overridden_by(&self, other: &RetentionPolicy) -> Self {
+        Self {
+            max_age_hours: other.max_age_hours.or(self.max_age_hours),
+            max_total_mb: other.max_total_mb.or(self.max_total_mb),
+            max_count: other.max_count.or(self.max_count),
+        }
+    }
+
+    /// 合并两个覆盖策略，每项取较宽松的值
+    fn merge_lenient(&self, other: &RetentionPolicy) -> Self {
+        Self {
+            max_age_hours: lenient(self.max_age_hours, other.max_age_hours),
+            max_total_mb: lenient(self.max_total_mb, other.max_total_mb),
+            max_count: lenient(self.max_count, other.max_count),
+        }
+    }
+
+    /// 选出要删除的文件，返回下标，`items` 须按修改时间从旧到新排序
+    ///
+    /// 先删除超过保留时间的文件，剩余文件的数量或总大小超出上限时再从最旧的开始删除
+    pub fn select_expired(&self, items: &[RetainedItem], now: f64) -> Vec<usize> {
+        let mut expired = vec![false; items.len()];
+        if let Some(hours) = self.max_age_hours {
+            let cutoff = now - hours * 3600.0;
+            for (i, item) in items.iter().enumerate() {
+                expired[i] = !item.protected && item.modified < cutoff;
+            }
+        }
+
+        let mut count = expired.iter().filter(|e| !**e).count();
+        let mut total: u64 = items.iter().zip(&expired).filter(|(_, e)| !**e).map(|(item, _)| item.size).sum();
+        let max_bytes = self.max_total_mb.map(|mb| mb.saturating_mul(1024 * 1024));
+        for (i, item) in items.iter().enumerate() {
+            let over_count = self.max_count.is_some_and(|max| count > max);
+            let over_size = max_bytes.is_some_and(|max| total > max);
+            if !over_count && !over_size {
+                break;
+            }
+            if expired[i] || item.protected {
+                continue;
+            }
+            expired[i] = true;
+            count -= 1;
+            total -= item.size;
+        }
+
+        expired.iter().enumerate().filter(|(_, e)| **e).map(|(i, _)| i).collect()
+    }
+}
+
+/// 视频源或标签对默认策略的覆盖
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RetentionOverride {
+    #[serde(default)]
+    pub clips: Option<RetentionPolicy>,
+    #[serde(default)]
+    pub recordings: Option<RetentionPolicy>,
+}
+
+fn default_interval_seconds() -> u64 {
+    300
+}
+
+fn default_clips_policy() -> RetentionPolicy {
+    RetentionPolicy {
+        max_age_hours: Some(168.0),
+        max_total_mb: Some(10240),
+        max_count: None,
+    }
+}
+
+/// 保留策略配置
+///
+/// 剪辑和录像各有默认策略，可以按视频源或标签覆盖。视频源的覆盖优先于标签的覆盖，
+/// 视频源有多个标签匹配时每项限制取最宽松的值。
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetentionRules {
+    /// 清理间隔（秒）
+    #[serde(default = "default_interval_seconds")]
+    pub interval_seconds: u64,
+    /// 剪辑的默认策略，没有覆盖的剪辑共用这一组限制
+    #[serde(default = "default_clips_policy")]
+    pub clips: RetentionPolicy,
+    /// 录像的默认策略，按视频源分别计算，默认不删除
+    #[serde(default)]
+    pub recordings: RetentionPolicy,
+    #[serde(default)]
+    pub sources: HashMap<String, RetentionOverride>,
+    #[serde(default)]
+    pub tags: HashMap<String, RetentionOverride>,
+}
+
+impl Default for RetentionRules {
+    fn default() -> Self {
+        Self {
+            interval_seconds: default_interval_seconds(),
+            clips: default_clips_policy(),
+            recordings: RetentionPolicy::default(),
+            sources: HashMap::new(),
+            tags: HashMap::new(),
+        }
+    }
+}
+
+impl RetentionRules {
+    /// 从配置文件加载，文件不存在时使用默认策略
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !Path::new(path).exists() {
+            tracing::info!("Retention file {} not found, using default retention", path);
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("读取保留策略文件失败 {}: {}", path, e))?;
+        let rules: Self = serde_json::from_str(&content)
+            .map_err(|e| format!("解析保留策略文件失败 {}: {}", path, e))?;
+        rules.validate()?;
+
+        tracing::info!(
+            "Loaded retention rules from {} ({} source overrides, {} tag overrides)",
+            path,
+            rules.sources.len(),
+            rules.tags.len()
+        );
+        Ok(rules)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(10..=86400).contains(&self.interval_seconds) {
+            return Err("保留策略的 interval_seconds 必须在 10-86400 之间".to_string());
+        }
+        self.clips.validate("clips")?;
+        self.recordings.validate("recordings")?;
+        for (scope, overrides) in [("sources", &self.sources), ("tags", &self.tags)] {
+            for (name, entry) in overrides {
+                if let Some(policy) = &entry.clips {
+                    policy.validate(&format!("{}.{}.clips", scope, name))?;
+                }
+                if let Some(policy) = &entry.recordings {
+                    policy.validate(&format!("{}.{}.recordings", scope, name))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 视频源的覆盖策略：先合并各标签的覆盖，再用视频源自己的覆盖覆盖
+    fn source_override(
+        &self,
+        source_id: &str,
+        source: Option<&Source>,
+        select: impl Fn(&RetentionOverride) -> Option<&RetentionPolicy>,
+    ) -> Option<RetentionPolicy> {
+        let by_tags = source
+            .into_iter()
+            .flat_map(|source| source.tags.iter())
+            .filter_map(|tag| self.tags.get(tag).and_then(&select))
+            .fold(None, |merged: Option<RetentionPolicy>, policy| {
+                Some(merged.map_or_else(|| policy.clone(), |merged| merged.merge_lenient(policy)))
+            });
+        let by_source = self.sources.get(source_id).and_then(&select);
+        match (by_tags, by_source) {
+            (Some(tags), Some(own)) => Some(tags.overridden_by(own)),
+            (tags, own) => tags.or_else(|| own.cloned()),
+        }
+    }
+
+    /// 视频源剪辑的策略，没有覆盖时返回None，此时剪辑与其他剪辑共用默认策略
+    pub fn clip_policy(&self, source_id: &str, source: Option<&Source>) -> Option<RetentionPolicy> {
+        self.source_override(source_id, source, |o| o.clips.as_ref())
+            .map(|policy| self.clips.overridden_by(&policy))
+    }
+
+    /// 视频源录像的策略
+    pub fn recording_policy(&self, source_id: &str, source: Option<&Source>) -> RetentionPolicy {
+        match self.source_override(source_id, source, |o| o.recordings.as_ref()) {
+            Some(policy) => self.recordings.overridden_by(&policy),
+            None => self.recordings.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: f64 = 3600.0;
+    const MB: u64 = 1024 * 1024;
+
+    fn item(age_hours: f64, size_mb: u64, protected: bool, now: f64) -> RetainedItem {
+        RetainedItem { modified: now - age_hours * HOUR, size: size_mb * MB, protected }
+    }
+
+    fn source(id: &str, tags: &[&str]) -> Source {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "url": "rtsp://camera/stream",
+            "tags": tags,
+            "created_at": 0,
+            "updated_at": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn age_cutoff_then_count_and_size() {
+        let now = 1_000_000.0;
+        // 从旧到新
+        let items = [
+            item(50.0, 10, false, now),
+            item(30.0, 10, false, now),
+            item(20.0, 10, false, now),
+            item(10.0, 10, false, now),
+            item(5.0, 10, false, now),
+            item(1.0, 10, false, now),
+        ];
+
+        let age = RetentionPolicy { max_age_hours: Some(24.0), ..Default::default() };
+        assert_eq!(age.select_expired(&items, now), vec![0, 1]);
+
+        // 超龄删除后剩4个，数量上限再删掉最旧的1个
+        let age_count = RetentionPolicy { max_age_hours: Some(24.0), max_count: Some(3), ..Default::default() };
+        assert_eq!(age_count.select_expired(&items, now), vec![0, 1, 2]);
+
+        // 剩余40MB，总量上限25MB再删掉最旧的2个
+        let age_size = RetentionPolicy { max_age_hours: Some(24.0), max_total_mb: Some(25), ..Default::default() };
+        assert_eq!(age_size.select_expired(&items, now), vec![0, 1, 2, 3]);
+
+        // 两个上限都设置时满足较严格的那个
+        let all = RetentionPolicy { max_age_hours: Some(24.0), max_total_mb: Some(35), max_count: Some(2) };
+        assert_eq!(all.select_expired(&items, now), vec![0, 1, 2, 3]);
+
+        assert!(RetentionPolicy::default().select_expired(&items, now).is_empty());
+    }
+
+    #[test]
+    fn protected_items_count_but_are_kept() {
+        let now = 1_000_000.0;
+        let items = [
+            item(50.0, 10, true, now),
+            item(40.0, 10, false, now),
+            item(30.0, 10, true, now),
+            item(2.0, 10, false, now),
+            item(1.0, 10, true, now),
+        ];
+
+        let age = RetentionPolicy { max_age_hours: Some(24.0), ..Default::default() };
+        assert_eq!(age.select_expired(&items, now), vec![1]);
+
+        // 受保护的文件计入数量，所以删除所有未受保护的也只剩3个
+        let count = RetentionPolicy { max_count: Some(2), ..Default::default() };
+        assert_eq!(count.select_expired(&items, now), vec![1, 3]);
+
+        let size = RetentionPolicy { max_total_mb: Some(35), ..Default::default() };
+        assert_eq!(size.select_expired(&items, now), vec![1, 3]);
+
+        let size = RetentionPolicy { max_total_mb: Some(40), ..Default::default() };
+        assert_eq!(size.select_expired(&items, now), vec![1]);
+    }
+
+    #[test]
+    fn clip_policy_merges_tags_leniently_then_applies_source_override() {
+        let rules: RetentionRules = serde_json::from_value(serde_json::json!({
+            "clips": {"max_age_hours": 24, "max_total_mb": 1000},
+            "tags": {
+                "evidence": {"clips": {"max_age_hours": 720, "max_count": 10}},
+                "lobby": {"clips": {"max_age_hours": 48, "max_count": 50}},
+                "unused": {"recordings": {"max_age_hours": 1}}
+            },
+            "sources": {
+                "gate": {"clips": {"max_count": 5}},
+                "door": {"clips": {"max_total_mb": 10}}
+            }
+        }))
+        .unwrap();
+
+        // 没有覆盖时与其他剪辑共用默认策略
+        assert!(rules.clip_policy("plain", Some(&source("plain", &["unused"]))).is_none());
+        assert!(rules.clip_policy("missing", None).is_none());
+
+        // 多个标签每项取较宽松的值，未覆盖的项沿用默认
+        let policy = rules.clip_policy("hall", Some(&source("hall", &["evidence", "lobby"]))).unwrap();
+        assert_eq!(policy.max_age_hours, Some(720.0));
+        assert_eq!(policy.max_count, Some(50));
+        assert_eq!(policy.max_total_mb, Some(1000));
+
+        // 视频源的覆盖优先于标签，即使更严格
+        let policy = rules.clip_policy("gate", Some(&source("gate", &["evidence", "lobby"]))).unwrap();
+        assert_eq!(policy.max_age_hours, Some(720.0));
+        assert_eq!(policy.max_count, Some(5));
+        assert_eq!(policy.max_total_mb, Some(1000));
+
+        // 只有视频源覆盖，视频源已删除时同样生效
+        let policy = rules.clip_policy("door", None).unwrap();
+        assert_eq!(policy.max_age_hours, Some(24.0));
+        assert_eq!(policy.max_total_mb, Some(10));
+        assert_eq!(policy.max_count, None);
+    }
+}
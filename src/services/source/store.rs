@@ -0,0 +1,374 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::services::video::{validate_masks, PrivacyMask};
+use crate::utils::now_secs;
+
+/// 预录缓冲的最大时长（秒）
+pub const MAX_BUFFER_SECONDS: u32 = 600;
+
+/// 视频源（摄像头）定义，保存在服务端，包含访问凭据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Source {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 隐私遮挡，该视频源的所有截图和视频都会应用
+    #[serde(default)]
+    pub masks: Vec<PrivacyMask>,
+    /// 是否持续录像
+    #[serde(default)]
+    pub record: bool,
+    /// 预录缓冲时长（秒），只保留最近这段时间的画面，持续录像时不需要
+    #[serde(default)]
+    pub buffer_seconds: Option<u32>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// 对外展示的视频源信息，不包含密码，URL中的凭据被去除
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceInfo {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub username: Option<String>,
+    pub has_password: bool,
+    pub profile: Option<String>,
+    pub tags: Vec<String>,
+    pub masks: Vec<PrivacyMask>,
+    pub record: bool,
+    pub buffer_seconds: Option<u32>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// 创建或更新视频源的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceInput {
+    /// 可选的自定义ID，只允许字母、数字、`-` 和 `_`，创建时不指定则自动生成
+    pub id: Option<String>,
+    pub name: String,
+    pub url: String,
+    pub username: Option<String>,
+    /// 更新时不传表示保留原密码，传空字符串表示清除
+    pub password: Option<String>,
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub masks: Vec<PrivacyMask>,
+    #[serde(default)]
+    pub record: bool,
+    pub buffer_seconds: Option<u32>,
+}
+
+/// 解析后的拉流输入
+#[derive(Debug, Clone)]
+pub struct StreamInput {
+    /// 带凭据的实际拉流地址，只用于调用ffmpeg，不应出现在日志和响应中
+    pub url: String,
+    /// 视频源指定的摄像头配置
+    pub profile: Option<String>,
+    /// 用于日志和通知的展示名称
+    pub label: String,
+    /// 视频源登记的隐私遮挡，直接指定URL时为空
+    pub masks: Vec<PrivacyMask>,
+}
+
+impl Source {
+    /// 转换为对外展示信息
+    pub fn info(&self) -> SourceInfo {
+        SourceInfo {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            url: redact_url(&self.url),
+            username: self.username.clone(),
+            has_password: self.password.as_deref().is_some_and(|p| !p.is_empty()),
+            profile: self.profile.clone(),
+            tags: self.tags.clone(),
+            masks: self.masks.clone(),
+            record: self.record,
+            buffer_seconds: self.buffer_seconds,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    /// 生成带凭据的拉流地址
+    pub fn stream_url(&self) -> Result<String, String> {
+        let username = self.username.as_deref().filter(|u| !u.is_empty());
+        let password = self.password.as_deref().filter(|p| !p.is_empty());
+        if username.is_none() && password.is_none() {
+            return Ok(self.url.clone());
+        }
+
+        let mut url = Url::parse(&self.url)
+            .map_err(|e| format!("视频源URL无效 {}: {}", self.id, e))?;
+        url.set_username(username.unwrap_or_default())
+            .map_err(|_| format!("视频源URL不支持设置用户名: {}", self.id))?;
+        url.set_password(password)
+            .map_err(|_| format!("视频源URL不支持设置密码: {}", self.id))?;
+
+        Ok(url.to_string())
+    }
+}
+
+/// 去除URL中的用户名和密码
+pub fn redact_url(raw: &str) -> String {
+    match Url::parse(raw) {
+        Ok(mut url) if !url.username().is_empty() || url.password().is_some() => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        _ => raw.to_string(),
+    }
+}
+
+/// 去除文本中所有URL的用户名和密码
+///
+/// ffmpeg/ffprobe报错时会原样输出拉流地址，地址可能带引号或后跟冒号，无法按URL整体解析，
+/// 所以逐个找到 `://` 后的主机部分，去掉其中最后一个 `@` 之前的内容
+pub fn redact_text(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("://") {
+        let (head, tail) = rest.split_at(pos + 3);
+        redacted.push_str(head);
+        let authority_len = tail
+            .find(|c: char| matches!(c, '/' | '?' | '#' | '\'' | '"' | '<' | '>') || c.is_whitespace())
+            .unwrap_or(tail.len());
+        let authority = &tail[..authority_len];
+        match authority.rfind('@') {
+            Some(at) => redacted.push_str(&authority[at + 1..]),
+            None => redacted.push_str(authority),
+        }
+        rest = &tail[authority_len..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// 视频源目录
+///
+/// 保存在JSON文件中，每次修改后整体写回（先写临时文件再重命名）。
+#[derive(Clone)]
+pub struct SourceStore {
+    path: PathBuf,
+    sources: Arc<RwLock<BTreeMap<String, Source>>>,
+}
+
+impl SourceStore {
+    /// 创建空目录，不读取文件，首次修改时写入该路径
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+            sources: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+
+    /// 从文件加载视频源，文件不存在时创建空目录
+    pub fn load(path: &str) -> Result<Self, String> {
+        let store = Self::new(path);
+        let sources = if Path::new(path).exists() {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| format!("读取视频源文件失败 {}: {}", path, e))?;
+            let list: Vec<Source> = serde_json::from_str(&content)
+                .map_err(|e| format!("解析视频源文件失败 {}: {}", path, e))?;
+            list.into_iter().map(|s| (s.id.clone(), s)).collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        tracing::info!("Loaded {} sources from {}", sources.len(), path);
+
+        *store.sources.write().unwrap() = sources;
+        Ok(store)
+    }
+
+    /// 列出视频源，可按标签过滤
+    pub fn list(&self, tag: Option<&str>) -> Vec<Source> {
+        self.sources.read().unwrap()
+            .values()
+            .filter(|s| tag.is_none_or(|tag| s.tags.iter().any(|t| t == tag)))
+            .cloned()
+            .collect()
+    }
+
+    /// 按ID查找视频源
+    pub fn get(&self, id: &str) -> Option<Source> {
+        self.sources.read().unwrap().get(id).cloned()
+    }
+
+    /// 创建视频源
+    pub fn create(&self, input: SourceInput) -> Result<Source, String> {
+        Self::validate(&input)?;
+
+        let id = match &input.id {
+            Some(id) => id.clone(),
+            None => Uuid::new_v4().to_string(),
+        };
+
+        let mut sources = self.sources.write().unwrap();
+        if sources.contains_key(&id) {
+            return Err(format!("视频源已存在: {}", id));
+        }
+
+        let now = now_secs();
+        let source = Source {
+            id: id.clone(),
+            name: input.name,
+            url: input.url,
+            username: input.username,
+            password: input.password.filter(|p| !p.is_empty()),
+            profile: input.profile,
+            tags: input.tags,
+            masks: input.masks,
+            record: input.record,
+            buffer_seconds: input.buffer_seconds,
+            created_at: now,
+            updated_at: now,
+        };
+
+        sources.insert(id, source.clone());
+        self.persist(&sources)?;
+
+        tracing::info!("Source {} created", source.id);
+        Ok(source)
+    }
+
+    /// 更新视频源，返回None表示不存在
+    pub fn update(&self, id: &str, input: SourceInput) -> Result<Option<Source>, String> {
+        Self::validate(&input)?;
+
+        let mut sources = self.sources.write().unwrap();
+        let Some(existing) = sources.get(id) else {
+            return Ok(None);
+        };
+
+        let password = match input.password {
+            Some(password) if password.is_empty() => None,
+            Some(password) => Some(password),
+            None => existing.password.clone(),
+        };
+
+        let source = Source {
+            id: id.to_string(),
+            name: input.name,
+            url: input.url,
+            username: input.username,
+            password,
+            profile: input.profile,
+            tags: input.tags,
+            masks: input.masks,
+            record: input.record,
+            buffer_seconds: input.buffer_seconds,
+            created_at: existing.created_at,
+            updated_at: now_secs(),
+        };
+
+        sources.insert(id.to_string(), source.clone());
+        self.persist(&sources)?;
+
+        tracing::info!("Source {} updated", id);
+        Ok(Some(source))
+    }
+
+    /// 删除视频源，返回是否存在
+    pub fn delete(&self, id: &str) -> Result<bool, String> {
+        let mut sources = self.sources.write().unwrap();
+        if sources.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.persist(&sources)?;
+
+        tracing::info!("Source {} deleted", id);
+        Ok(true)
+    }
+
+    /// 解析请求中的视频源ID或URL，二者必须且只能提供一个
+    pub fn resolve(&self, source_id: Option<&str>, url: Option<&str>) -> Result<StreamInput, String> {
+        match (source_id, url) {
+            (Some(id), None) => {
+                let source = self.get(id).ok_or_else(|| format!("视频源不存在: {}", id))?;
+                Ok(StreamInput {
+                    url: source.stream_url()?,
+                    profile: source.profile.clone(),
+                    label: format!("{} ({})", source.name, source.id),
+                    masks: source.masks.clone(),
+                })
+            }
+            (None, Some(url)) if !url.is_empty() => Ok(StreamInput {
+                url: url.to_string(),
+                profile: None,
+                label: redact_url(url),
+                masks: Vec::new(),
+            }),
+            (Some(_), Some(_)) => Err("source_id 和 url 不能同时指定".to_string()),
+            _ => Err("必须指定 source_id 或 url".to_string()),
+        }
+    }
+
+    fn validate(input: &SourceInput) -> Result<(), String> {
+        if let Some(id) = &input.id
+            && (id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        {
+            return Err(format!("视频源ID只能包含字母、数字、- 和 _: {}", id));
+        }
+        if input.name.trim().is_empty() {
+            return Err("视频源名称不能为空".to_string());
+        }
+        if input.url.trim().is_empty() {
+            return Err("视频源URL不能为空".to_string());
+        }
+        if let Some(seconds) = input.buffer_seconds
+            && !(1..=MAX_BUFFER_SECONDS).contains(&seconds)
+        {
+            return Err(format!("buffer_seconds 必须在 1-{} 之间", MAX_BUFFER_SECONDS));
+        }
+        validate_masks(&input.masks)
+    }
+
+    /// 写回文件
+    fn persist(&self, sources: &BTreeMap<String, Source>) -> Result<(), String> {
+        let list: Vec<&Source> = sources.values().collect();
+        let content = serde_json::to_string_pretty(&list)
+            .map_err(|e| format!("序列化视频源失败: {}", e))?;
+
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("创建视频源目录失败: {}", e))?;
+        }
+
+        // 文件中有明文密码，只允许服务用户读写；mode只在创建时生效，所以先删除残留的临时文件
+        let tmp = self.path.with_extension("json.tmp");
+        let _ = std::fs::remove_file(&tmp);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&tmp)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .map_err(|e| format!("写入视频源文件失败: {}", e))?;
+        std::fs::rename(&tmp, &self.path)
+            .map_err(|e| format!("保存视频源文件失败: {}", e))?;
+
+        Ok(())
+    }
+}
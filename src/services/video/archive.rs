@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{ArchiveHandler, ClipOutput, CodecPreset, ProgressCallback, VideoSnapshotService};
+
+/// 写入ffconcat列表，`files` 须为绝对路径
+///
+/// 不写duration，由concat demuxer读取每个片段的实际时长
+fn write_ffconcat(list_path: &Path, files: &[PathBuf]) -> Result<(), String> {
+    let mut list = "ffconcat version 1.0\n".to_string();
+    for file in files {
+        let path = file.to_string_lossy().replace('\'', "'\\''");
+        list.push_str(&format!("file '{}'\n", path));
+    }
+    std::fs::write(list_path, list)
+        .map_err(|e| format!("Failed to write concat list: {}", e))
+}
+
+impl VideoSnapshotService {
+    /// 从按时间排序的录像片段剪辑，`offset` 是剪辑起点相对第一个片段开头的秒数，返回文件名
+    ///
+    /// 重新编码的输出本身就精确到帧。直接复制时起点只能落在关键帧上，录像片段都从关键帧开始，
+    /// 所以起点不在片段开头时未指定编码的剪辑整段重新编码为H.264，指定 `copy` 时报错；结尾由 `-t` 按帧截断。
+    /// 叠加的时间按 `start_time`（第一帧的录制时间）逐帧计算
+    pub async fn clip_archive(
+        &self,
+        segments: &[PathBuf],
+        offset: f64,
+        start_time: f64,
+        duration: f64,
+        output: &ClipOutput,
+        on_progress: Option<ProgressCallback>,
+    ) -> Result<String, String> {
+        if segments.is_empty() {
+            return Err("该时间段没有录像".to_string());
+        }
+        let work_dir = tempfile::tempdir()
+            .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+
+        let mut output = output.clone();
+        let resolved = self.resolve_output(&output)?;
+        if offset > 0.0 {
+            if resolved.is_copy() {
+                return Err("剪辑起点不在录像片段开头，直接复制无法精确到帧，请指定转码预设".to_string());
+            }
+            // 未指定编码时默认会直接复制，改为重新编码
+            if !resolved.has_codec() {
+                output.codec = Some(CodecPreset::H264);
+            }
+        }
+        // 叠加的时间是画面的录制时间，而不是剪辑时的当前时间
+        if let Some(overlay) = output.overlay.as_mut() {
+            overlay.time_base = Some(start_time);
+        }
+
+        let list_path = work_dir.path().join("segments.ffconcat");
+        write_ffconcat(&list_path, segments)?;
+        let handler = Arc::new(ArchiveHandler);
+        self.clip_with_handler(handler, &list_path.to_string_lossy(), offset, duration, &output, on_progress).await
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::services::source::redact_url;
+use super::{FfmpegCommand, PartialOutputGuard, VideoSnapshotService};
+
+/// Opus编码器支持的采样率
+const OPUS_SAMPLE_RATES: &[u32] = &[8000, 12000, 16000, 24000, 48000];
+
+/// 音频输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AudioFormat {
+    #[default]
+    Wav,
+    Flac,
+    Mp3,
+    Opus,
+}
+
+impl AudioFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "wav" => Ok(Self::Wav),
+            "flac" => Ok(Self::Flac),
+            "mp3" => Ok(Self::Mp3),
+            "opus" => Ok(Self::Opus),
+            _ => Err(format!("不支持的音频格式: {}，可选 wav/flac/mp3/opus", name)),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+            Self::Mp3 => "mp3",
+            Self::Opus => "opus",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Wav => "audio/wav",
+            Self::Flac => "audio/flac",
+            Self::Mp3 => "audio/mpeg",
+            Self::Opus => "audio/ogg",
+        }
+    }
+
+    /// 有损格式才有码率设置
+    pub fn is_lossy(&self) -> bool {
+        matches!(self, Self::Mp3 | Self::Opus)
+    }
+
+    fn codec_args(&self, bitrate_kbps: Option<u32>) -> Vec<String> {
+        let mut args = match self {
+            Self::Wav => vec!["-c:a".to_string(), "pcm_s16le".to_string(), "-f".to_string(), "wav".to_string()],
+            Self::Flac => vec!["-c:a".to_string(), "flac".to_string(), "-f".to_string(), "flac".to_string()],
+            Self::Mp3 => vec!["-c:a".to_string(), "libmp3lame".to_string(), "-f".to_string(), "mp3".to_string()],
+            Self::Opus => vec!["-c:a".to_string(), "libopus".to_string(), "-f".to_string(), "ogg".to_string()],
+        };
+        if self.is_lossy() {
+            let default = if *self == Self::Opus { 64 } else { 128 };
+            args.extend(["-b:a".to_string(), format!("{}k", bitrate_kbps.unwrap_or(default))]);
+        }
+        args
+    }
+}
+
+/// 音频提取参数
+#[derive(Debug, Clone, Default)]
+pub struct AudioOptions {
+    pub format: AudioFormat,
+    /// 输出采样率，为空时保持原采样率
+    pub sample_rate: Option<u32>,
+    /// 输出声道数，1为下混为单声道
+    pub channels: Option<u32>,
+    /// EBU R128响度标准化
+    pub loudnorm: bool,
+    /// mp3/opus码率（kbps）
+    pub bitrate_kbps: Option<u32>,
+}
+
+impl AudioOptions {
+    /// 校验参数范围
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(rate) = self.sample_rate {
+            if !(8000..=192000).contains(&rate) {
+                return Err("sample_rate 必须在 8000-192000 之间".to_string());
+            }
+            if self.format == AudioFormat::Opus && !OPUS_SAMPLE_RATES.contains(&rate) {
+                return Err("opus 只支持 8000/12000/16000/24000/48000 采样率".to_string());
+            }
+        }
+        if let Some(channels) = self.channels
+            && !(1..=8).contains(&channels)
+        {
+            return Err("channels 必须在 1-8 之间".to_string());
+        }
+        if let Some(bitrate) = self.bitrate_kbps {
+            if !self.format.is_lossy() {
+                return Err("bitrate 只对 mp3/opus 生效".to_string());
+            }
+            if !(8..=320).contains(&bitrate) {
+                return Err("bitrate 必须在 8-320 kbps 之间".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// 编码参数，替代剪辑的 `-c:v`/`-c:a` 部分
+    fn codec_args(&self) -> Vec<String> {
+        // 只取第一条音轨，没有音轨时ffmpeg直接报错
+        let mut args = vec![
+            "-map".to_string(), "0:a:0".to_string(),
+            "-vn".to_string(),
+        ];
+        if self.loudnorm {
+            args.extend(["-af".to_string(), "loudnorm=I=-16:TP=-1.5:LRA=11".to_string()]);
+        }
+        if let Some(rate) = self.sample_rate {
+            args.extend(["-ar".to_string(), rate.to_string()]);
+        } else if self.loudnorm {
+            // loudnorm内部会上采样到192kHz，未指定时恢复为常用采样率
+            args.extend(["-ar".to_string(), "48000".to_string()]);
+        }
+        if let Some(channels) = self.channels {
+            args.extend(["-ac".to_string(), channels.to_string()]);
+        }
+        args.extend(self.format.codec_args(self.bitrate_kbps));
+        args
+    }
+}
+
+impl VideoSnapshotService {
+    /// 提取视频流一段的音频，保存到clips目录，返回文件名
+    pub async fn extract_audio(
+        &self,
+        url: &str,
+        start: f64,
+        duration: f64,
+        profile: Option<&str>,
+        options: &AudioOptions,
+    ) -> Result<String, String> {
+        tracing::info!("Starting extract_audio for URL: {}, start: {}, duration: {}", redact_url(url), start, duration);
+
+        let filename = format!("{}.{}", Uuid::new_v4(), options.format.extension());
+        let output_path = self.clip_path(&filename);
+
+        let handler = self.resolve_handler(url, profile)?;
+        let args = handler.build_clip_args(url, start, duration, options.codec_args(), &output_path);
+
+        let mut partial_output = PartialOutputGuard::new(&output_path);
+
+        let limit = handler.clip_timeout_margin() + Duration::from_secs_f64(duration);
+        let output = FfmpegCommand::new(args)
+            .timeout(limit)
+            .run()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::error!("FFmpeg audio extraction failed - stderr: {}", stderr);
+            if stderr.contains("matches no streams") {
+                return Err("视频流没有音轨".to_string());
+            }
+            return Err(format!("FFmpeg audio extraction failed: {}", stderr));
+        }
+
+        match std::fs::metadata(&output_path) {
+            Ok(metadata) if metadata.len() > 0 => {
+                tracing::info!("Successfully extracted audio: {}, size: {} bytes", filename, metadata.len());
+            }
+            _ => return Err("Generated audio file is empty".to_string()),
+        }
+
+        partial_output.keep();
+        Ok(filename)
+    }
+}
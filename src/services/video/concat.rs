@@ -0,0 +1,206 @@
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::{apply_masks, FfmpegCommand, FilterGraph, OutputContainer, PartialOutputGuard, PrivacyMask, VideoSnapshotService};
+
+/// 拼接的一段
+#[derive(Debug, Clone)]
+pub struct ConcatSegment {
+    /// 带凭据的拉流地址
+    pub url: String,
+    pub start: f64,
+    pub duration: f64,
+    pub profile: Option<String>,
+    /// 视频源登记的隐私遮挡
+    pub masks: Vec<PrivacyMask>,
+}
+
+/// 拼接输出参数，各段统一缩放到同一分辨率和帧率
+#[derive(Debug, Clone)]
+pub struct ConcatOptions {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub container: OutputContainer,
+}
+
+impl Default for ConcatOptions {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            fps: 25,
+            container: OutputContainer::Mp4,
+        }
+    }
+}
+
+impl ConcatOptions {
+    /// 各段统一转码的参数，输出为MPEG-TS，方便concat demuxer无损拼接
+    ///
+    /// 画面先遮挡再等比缩放后补黑边，音频统一为48kHz立体声AAC，没有音轨的段补静音。
+    /// 遮挡蒙版写入 `work_dir`
+    fn segment_codec_args(&self, has_audio: bool, masks: &[PrivacyMask], work_dir: &Path) -> Result<Vec<String>, String> {
+        let mut graph = FilterGraph::new("in");
+        apply_masks(masks, &mut graph, work_dir)?;
+        graph.push(format!(
+            "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps}",
+            w = self.width,
+            h = self.height,
+            fps = self.fps
+        ));
+
+        let mut args = Vec::new();
+        if has_audio {
+            args.extend([
+                "-map".to_string(), "0:v:0".to_string(),
+                "-map".to_string(), "0:a:0".to_string(),
+                "-af".to_string(), "aresample=48000,aformat=channel_layouts=stereo".to_string(),
+            ]);
+        } else {
+            args.extend([
+                "-filter_complex".to_string(), "anullsrc=r=48000:cl=stereo[silence]".to_string(),
+                "-map".to_string(), "0:v:0".to_string(),
+                "-map".to_string(), "[silence]".to_string(),
+            ]);
+        }
+        args.extend([
+            "-vf".to_string(), graph.into_vf().unwrap_or_default(),
+            "-c:v".to_string(), "libx264".to_string(),
+            "-preset".to_string(), "fast".to_string(),
+            "-crf".to_string(), "23".to_string(),
+            "-pix_fmt".to_string(), "yuv420p".to_string(),
+            "-c:a".to_string(), "aac".to_string(),
+            "-b:a".to_string(), "128k".to_string(),
+            "-f".to_string(), "mpegts".to_string(),
+        ]);
+        Ok(args)
+    }
+}
+
+/// 拼接失败，`segment` 为失败段的下标
+#[derive(Debug, Clone)]
+pub struct ConcatError {
+    pub segment: Option<usize>,
+    pub message: String,
+}
+
+impl ConcatError {
+    fn segment(index: usize, message: impl Into<String>) -> Self {
+        Self { segment: Some(index), message: message.into() }
+    }
+
+    fn output(message: impl Into<String>) -> Self {
+        Self { segment: None, message: message.into() }
+    }
+}
+
+impl fmt::Display for ConcatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.segment {
+            Some(index) => write!(f, "第 {} 段失败: {}", index + 1, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl VideoSnapshotService {
+    /// 按顺序截取多段并拼接为一个文件，保存到clips目录，返回文件名
+    ///
+    /// 各段先统一转码为相同参数的MPEG-TS，再用concat demuxer直接复制拼接
+    pub async fn concat_clips(
+        &self,
+        segments: &[ConcatSegment],
+        options: &ConcatOptions,
+    ) -> Result<String, ConcatError> {
+        tracing::info!("Starting concat_clips with {} segments", segments.len());
+
+        let temp_dir = tempfile::tempdir()
+            .map_err(|e| ConcatError::output(format!("Failed to create temp dir: {}", e)))?;
+        let mut list = String::new();
+
+        for (i, segment) in segments.iter().enumerate() {
+            let handler = self.resolve_handler(&segment.url, segment.profile.as_deref())
+                .map_err(|e| ConcatError::segment(i, e))?;
+
+            // 先探测是否有音轨，没有时补静音，保证各段流结构一致
+            let probe = self.probe(&segment.url, segment.profile.as_deref()).await
+                .map_err(|e| ConcatError::segment(i, e))?;
+            if probe.video().is_none() {
+                return Err(ConcatError::segment(i, "视频流没有画面"));
+            }
+
+            let segment_path = temp_dir.path().join(format!("segment_{:03}.ts", i));
+            let segment_path = segment_path.to_string_lossy().into_owned();
+            let work_dir = temp_dir.path().join(format!("segment_{:03}", i));
+            std::fs::create_dir_all(&work_dir)
+                .map_err(|e| ConcatError::segment(i, format!("Failed to create temp dir: {}", e)))?;
+            let codec_args = options.segment_codec_args(probe.audio().is_some(), &segment.masks, &work_dir)
+                .map_err(|e| ConcatError::segment(i, e))?;
+            let args = handler.build_clip_args(&segment.url, segment.start, segment.duration, codec_args, &segment_path);
+
+            let limit = handler.clip_timeout_margin() + Duration::from_secs_f64(segment.duration);
+            let output = FfmpegCommand::new(args)
+                .timeout(limit)
+                .run()
+                .await
+                .map_err(|e| ConcatError::segment(i, e))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                tracing::error!("FFmpeg concat segment {} failed - stderr: {}", i, stderr);
+                return Err(ConcatError::segment(i, format!("FFmpeg failed: {}", stderr)));
+            }
+            match std::fs::metadata(&segment_path) {
+                Ok(metadata) if metadata.len() > 0 => {}
+                _ => return Err(ConcatError::segment(i, "没有截取到数据")),
+            }
+
+            list.push_str(&format!("file '{}'\n", segment_path.replace('\'', "'\\''")));
+            tracing::info!("Concat segment {} of {} ready", i + 1, segments.len());
+        }
+
+        let list_path = temp_dir.path().join("segments.txt");
+        std::fs::write(&list_path, list)
+            .map_err(|e| ConcatError::output(format!("Failed to write concat list: {}", e)))?;
+
+        let filename = format!("{}.{}", Uuid::new_v4(), options.container.extension());
+        let output_path = self.clip_path(&filename);
+        let mut partial_output = PartialOutputGuard::new(&output_path);
+
+        let mut args = vec![
+            "-f".to_string(), "concat".to_string(),
+            "-safe".to_string(), "0".to_string(),
+            "-i".to_string(), list_path.to_string_lossy().into_owned(),
+            "-c".to_string(), "copy".to_string(),
+        ];
+        if options.container != OutputContainer::Ts {
+            args.extend(["-bsf:a".to_string(), "aac_adtstoasc".to_string()]);
+        }
+        args.extend(options.container.muxer_args());
+        args.extend(["-y".to_string(), output_path.clone()]);
+
+        let total: f64 = segments.iter().map(|s| s.duration).sum();
+        let output = FfmpegCommand::new(args)
+            .timeout(Duration::from_secs(30) + Duration::from_secs_f64(total / 10.0))
+            .run()
+            .await
+            .map_err(ConcatError::output)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::error!("FFmpeg concat failed - stderr: {}", stderr);
+            return Err(ConcatError::output(format!("FFmpeg concat failed: {}", stderr)));
+        }
+
+        match std::fs::metadata(&output_path) {
+            Ok(metadata) if metadata.len() > 0 => {
+                tracing::info!("Successfully created concat clip: {}, size: {} bytes", filename, metadata.len());
+            }
+            _ => return Err(ConcatError::output("Generated concat file is empty")),
+        }
+
+        partial_output.keep();
+        Ok(filename)
+    }
+}
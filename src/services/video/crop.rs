@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+/// 像素坐标的最大值
+const MAX_PIXEL: f64 = 16384.0;
+
+/// 裁剪坐标单位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CropUnit {
+    /// 相对画面宽高的比例（0-1），与分辨率无关
+    #[default]
+    Ratio,
+    /// 原画面的像素，超出画面的部分自动收缩到画面内
+    Pixel,
+}
+
+/// 感兴趣区域裁剪，配合输出尺寸实现数字变焦
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CropRegion {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    #[serde(default)]
+    pub unit: CropUnit,
+}
+
+impl CropRegion {
+    /// 校验坐标范围
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [self.x, self.y, self.width, self.height];
+        if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return Err("crop 坐标必须是非负数".to_string());
+        }
+        match self.unit {
+            CropUnit::Ratio => {
+                if values.iter().any(|v| *v > 1.0) || self.x + self.width > 1.0 || self.y + self.height > 1.0 {
+                    return Err("crop 比例坐标必须在 0-1 之间且不超出画面".to_string());
+                }
+                if self.width <= 0.0 || self.height <= 0.0 {
+                    return Err("crop 宽高必须大于0".to_string());
+                }
+            }
+            CropUnit::Pixel => {
+                if values.iter().any(|v| v.fract() != 0.0 || *v > MAX_PIXEL) {
+                    return Err(format!("crop 像素坐标必须是 0-{} 之间的整数", MAX_PIXEL));
+                }
+                if self.width < 2.0 || self.height < 2.0 {
+                    return Err("crop 宽高至少为 2 像素".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// crop滤镜，宽高取偶数以满足yuv420p编码
+    pub(super) fn filter(&self) -> String {
+        match self.unit {
+            CropUnit::Ratio => format!(
+                "crop=w='trunc(iw*{}/2)*2':h='trunc(ih*{}/2)*2':x='iw*{}':y='ih*{}'",
+                self.width, self.height, self.x, self.y
+            ),
+            CropUnit::Pixel => format!(
+                "crop=w='trunc(min({},iw)/2)*2':h='trunc(min({},ih)/2)*2':x='min({},iw-ow)':y='min({},ih-oh)'",
+                self.width, self.height, self.x, self.y
+            ),
+        }
+    }
+}
@@ -0,0 +1,609 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use super::{validate_masks, CropRegion, OverlaySpec, PrivacyMask, ProbeResult};
+
+/// 剪辑输出容器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputContainer {
+    #[default]
+    Mp4,
+    Mkv,
+    Webm,
+    Mov,
+    Ts,
+}
+
+impl OutputContainer {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "mp4" => Ok(Self::Mp4),
+            "mkv" | "matroska" => Ok(Self::Mkv),
+            "webm" => Ok(Self::Webm),
+            "mov" => Ok(Self::Mov),
+            "ts" | "mpegts" => Ok(Self::Ts),
+            _ => Err(format!("不支持的输出容器: {}，可选 mp4/mkv/webm/mov/ts", name)),
+        }
+    }
+
+    /// 按文件扩展名识别容器
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?;
+        Self::parse(ext).ok()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mkv => "mkv",
+            Self::Webm => "webm",
+            Self::Mov => "mov",
+            Self::Ts => "ts",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "video/mp4",
+            Self::Mkv => "video/x-matroska",
+            Self::Webm => "video/webm",
+            Self::Mov => "video/quicktime",
+            Self::Ts => "video/mp2t",
+        }
+    }
+
+    /// ffmpeg的muxer名称
+    fn muxer(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mkv => "matroska",
+            Self::Webm => "webm",
+            Self::Mov => "mov",
+            Self::Ts => "mpegts",
+        }
+    }
+
+    /// 容器可以直接复制的视频编码
+    fn copyable_video(&self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 | Self::Mov => &["h264", "hevc", "mpeg4", "av1"],
+            Self::Webm => &["vp8", "vp9", "av1"],
+            Self::Ts => &["h264", "hevc", "mpeg2video"],
+            Self::Mkv => &[],
+        }
+    }
+
+    /// 容器可以直接复制的音频编码
+    fn copyable_audio(&self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 | Self::Mov => &["aac", "mp3"],
+            Self::Webm => &["opus", "vorbis"],
+            Self::Ts => &["aac", "mp3", "ac3"],
+            Self::Mkv => &[],
+        }
+    }
+
+    /// Matroska几乎可以容纳任何编码
+    fn accepts_any_codec(&self) -> bool {
+        *self == Self::Mkv
+    }
+
+    /// 默认音频编码参数
+    fn default_audio_args(&self) -> Vec<String> {
+        match self {
+            Self::Webm => vec!["-c:a".to_string(), "libopus".to_string(), "-b:a".to_string(), "96k".to_string()],
+            _ => vec!["-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), "128k".to_string()],
+        }
+    }
+
+    /// 容器相关的输出参数，放在输出路径之前
+    pub fn muxer_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if matches!(self, Self::Mp4 | Self::Mov) {
+            // moov放在文件头，浏览器可以边下边播
+            args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+        }
+        args.extend(["-f".to_string(), self.muxer().to_string()]);
+        args
+    }
+}
+
+/// 自包含的流媒体打包格式
+///
+/// 输出到clips下的独立子目录，浏览器可以按分片拖动播放，不需要下载整个文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipPackage {
+    /// HLS点播列表 + MPEG-TS分片
+    Hls,
+    /// DASH MPD + fMP4分片，同时生成引用同一组分片的HLS列表
+    Dash,
+}
+
+impl ClipPackage {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "hls" => Ok(Self::Hls),
+            "dash" => Ok(Self::Dash),
+            _ => Err(format!("不支持的打包格式: {}，可选 hls/dash", name)),
+        }
+    }
+
+    /// 分片使用的容器，决定可复制的编码
+    fn segment_container(&self) -> OutputContainer {
+        match self {
+            Self::Hls => OutputContainer::Ts,
+            Self::Dash => OutputContainer::Mp4,
+        }
+    }
+
+    /// 入口文件名
+    pub fn manifest_name(&self) -> &'static str {
+        match self {
+            Self::Hls => "index.m3u8",
+            Self::Dash => "manifest.mpd",
+        }
+    }
+
+    /// 打包参数，`output_dir` 为分片所在目录
+    fn muxer_args(&self, output_dir: &str) -> Vec<String> {
+        match self {
+            Self::Hls => vec![
+                "-f".to_string(), "hls".to_string(),
+                "-hls_time".to_string(), "4".to_string(),
+                "-hls_playlist_type".to_string(), "vod".to_string(),
+                "-hls_list_size".to_string(), "0".to_string(),
+                "-hls_segment_filename".to_string(), format!("{}/segment_%05d.ts", output_dir),
+            ],
+            Self::Dash => vec![
+                "-f".to_string(), "dash".to_string(),
+                "-seg_duration".to_string(), "4".to_string(),
+                "-use_template".to_string(), "1".to_string(),
+                "-use_timeline".to_string(), "1".to_string(),
+                "-hls_playlist".to_string(), "1".to_string(),
+            ],
+        }
+    }
+}
+
+/// 视频编码预设
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodecPreset {
+    Copy,
+    H264,
+    H265,
+    Vp9,
+    Av1,
+}
+
+impl CodecPreset {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "copy" => Ok(Self::Copy),
+            "h264" | "avc" => Ok(Self::H264),
+            "h265" | "hevc" => Ok(Self::H265),
+            "vp9" => Ok(Self::Vp9),
+            "av1" => Ok(Self::Av1),
+            _ => Err(format!("不支持的编码预设: {}，可选 copy/h264/h265/vp9/av1", name)),
+        }
+    }
+
+    /// 对应的ffprobe编码名称
+    fn codec_name(&self) -> Option<&'static str> {
+        match self {
+            Self::Copy => None,
+            Self::H264 => Some("h264"),
+            Self::H265 => Some("hevc"),
+            Self::Vp9 => Some("vp9"),
+            Self::Av1 => Some("av1"),
+        }
+    }
+
+    /// 检查容器是否支持该编码
+    pub fn check_container(&self, container: OutputContainer) -> Result<(), String> {
+        match self.codec_name() {
+            Some(codec) if !container.accepts_any_codec() && !container.copyable_video().contains(&codec) => {
+                Err(format!(
+                    "{} 容器不支持 {} 编码",
+                    container.extension(),
+                    codec
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 默认视频编码参数，`crf`/`preset` 为空时使用各编码器的推荐值
+    fn video_args(&self, container: OutputContainer, crf: Option<u32>, preset: Option<&str>) -> Vec<String> {
+        let mut args: Vec<String> = match self {
+            Self::Copy => vec!["-c:v".into(), "copy".into()],
+            Self::H264 => vec![
+                "-c:v".into(), "libx264".into(),
+                "-preset".into(), preset.unwrap_or("fast").into(),
+                "-crf".into(), crf.unwrap_or(23).to_string(),
+                "-pix_fmt".into(), "yuv420p".into(),
+            ],
+            Self::H265 => vec![
+                "-c:v".into(), "libx265".into(),
+                "-preset".into(), preset.unwrap_or("fast").into(),
+                "-crf".into(), crf.unwrap_or(28).to_string(),
+                "-pix_fmt".into(), "yuv420p".into(),
+            ],
+            // 恒定质量模式需要 -b:v 0
+            Self::Vp9 => vec![
+                "-c:v".into(), "libvpx-vp9".into(),
+                "-crf".into(), crf.unwrap_or(32).to_string(),
+                "-b:v".into(), "0".into(),
+                "-deadline".into(), preset.unwrap_or("realtime").into(),
+                "-cpu-used".into(), "8".into(),
+                "-row-mt".into(), "1".into(),
+            ],
+            Self::Av1 => vec![
+                "-c:v".into(), "libsvtav1".into(),
+                "-preset".into(), preset.unwrap_or("8").into(),
+                "-crf".into(), crf.unwrap_or(35).to_string(),
+            ],
+        };
+
+        // Apple播放器只识别hvc1标签的HEVC
+        if *self == Self::H265 && matches!(container, OutputContainer::Mp4 | OutputContainer::Mov) {
+            args.extend(["-tag:v".into(), "hvc1".into()]);
+        }
+        args
+    }
+}
+
+/// 命名编码配置
+///
+/// 在配置文件中定义，剪辑请求通过 `encoding` 字段按名称引用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodingProfile {
+    pub name: String,
+    pub description: Option<String>,
+    pub container: OutputContainer,
+    pub codec: CodecPreset,
+    pub crf: Option<u32>,
+    /// 编码器速度预设，含义取决于编码器
+    pub preset: Option<String>,
+    /// 音频编码器，None表示按容器和源编码自动选择
+    pub audio_codec: Option<String>,
+    pub audio_bitrate: Option<String>,
+    /// 追加在编码参数之后的额外ffmpeg参数
+    pub extra_args: Vec<String>,
+}
+
+impl Default for EncodingProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: None,
+            container: OutputContainer::Mp4,
+            codec: CodecPreset::H264,
+            crf: None,
+            preset: None,
+            audio_codec: None,
+            audio_bitrate: None,
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+impl EncodingProfile {
+    fn builtin() -> Vec<Self> {
+        vec![
+            Self {
+                name: "web".to_string(),
+                description: Some("MP4 H.264/AAC，兼容所有浏览器".to_string()),
+                ..Self::default()
+            },
+            Self {
+                name: "browser-webm".to_string(),
+                description: Some("WebM VP9/Opus".to_string()),
+                container: OutputContainer::Webm,
+                codec: CodecPreset::Vp9,
+                ..Self::default()
+            },
+            Self {
+                name: "archive".to_string(),
+                description: Some("MKV 原样复制全部音视频流".to_string()),
+                container: OutputContainer::Mkv,
+                codec: CodecPreset::Copy,
+                ..Self::default()
+            },
+        ]
+    }
+}
+
+/// 剪辑输出设置，随剪辑任务参数保存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClipOutput {
+    /// 输出容器，默认mp4，指定编码配置时默认使用配置中的容器
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<OutputContainer>,
+    /// 视频编码预设，优先于编码配置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<CodecPreset>,
+    /// 命名编码配置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// 打包为HLS/DASH，与container互斥
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<ClipPackage>,
+    /// 烧录到画面中的文字和水印，需要重新编码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay: Option<OverlaySpec>,
+    /// 隐私遮挡，需要重新编码
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub masks: Vec<PrivacyMask>,
+    /// 感兴趣区域裁剪，需要重新编码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<CropRegion>,
+    /// 输出宽度，只指定一边时按比例缩放，需要重新编码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// 输出高度
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+impl ClipOutput {
+    /// 是否需要经过视频滤镜，需要时不能直接复制视频流
+    pub fn has_filters(&self) -> bool {
+        !self.masks.is_empty()
+            || self.crop.is_some()
+            || self.width.is_some()
+            || self.height.is_some()
+            || self.overlay.as_ref().is_some_and(|overlay| !overlay.is_empty())
+    }
+
+    /// 校验遮挡、裁剪和输出尺寸
+    pub fn validate(&self) -> Result<(), String> {
+        validate_masks(&self.masks)?;
+        if let Some(crop) = &self.crop {
+            crop.validate()?;
+        }
+        for value in [self.width, self.height].into_iter().flatten() {
+            if !(16..=3840).contains(&value) || !value.is_multiple_of(2) {
+                return Err("width/height 必须是 16-3840 之间的偶数".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// 缩放滤镜，保持比例且宽高为偶数，同时指定宽高时缩放到框内
+    pub(super) fn scale_filter(&self) -> Option<String> {
+        match (self.width, self.height) {
+            (None, None) => None,
+            (Some(w), None) => Some(format!("scale={}:-2", w)),
+            (None, Some(h)) => Some(format!("scale=-2:{}", h)),
+            (Some(w), Some(h)) => Some(format!(
+                "scale={}:{}:force_original_aspect_ratio=decrease:force_divisible_by=2",
+                w, h
+            )),
+        }
+    }
+
+    /// 在请求的遮挡之前加上视频源登记的遮挡
+    pub fn with_masks(&self, masks: &[PrivacyMask]) -> Self {
+        let mut output = self.clone();
+        output.masks = masks.iter().chain(&self.masks).cloned().collect();
+        output
+    }
+}
+
+/// 解析后的输出编码方案
+#[derive(Debug, Clone)]
+pub struct ResolvedOutput {
+    pub container: OutputContainer,
+    pub package: Option<ClipPackage>,
+    /// 为空表示沿用流处理器/摄像头配置的默认编码
+    codec: Option<CodecPreset>,
+    profile: Option<EncodingProfile>,
+}
+
+impl ResolvedOutput {
+    /// 是否沿用流处理器的默认编码
+    ///
+    /// 流处理器按MP4选择复制还是重新编码，所以只有未指定编码且输出MP4文件时才沿用
+    pub fn uses_handler_codec(&self) -> bool {
+        self.codec.is_none() && self.package.is_none() && self.container == OutputContainer::Mp4
+    }
+
+    /// 是否明确指定了编码（请求或编码配置）
+    pub fn has_codec(&self) -> bool {
+        self.codec.is_some()
+    }
+
+    /// 是否指定了直接复制（包括编码配置中的 `copy`）
+    pub fn is_copy(&self) -> bool {
+        self.codec == Some(CodecPreset::Copy)
+    }
+
+    /// 容器或打包参数，`output_dir` 为打包输出的子目录
+    pub fn muxer_args(&self, output_dir: &str) -> Vec<String> {
+        match self.package {
+            Some(package) => package.muxer_args(output_dir),
+            None => self.container.muxer_args(),
+        }
+    }
+
+    /// 编码参数，放在 `-t` 之后、输出路径之前
+    ///
+    /// `probe` 用于判断视频能否直接复制、音频能否直接复制以及源是否有音轨。
+    /// 未指定编码时，探测到的视频编码容器可以容纳就直接复制，否则转码为H.264
+    pub fn codec_args(&self, probe: Option<&ProbeResult>) -> Result<Vec<String>, String> {
+        let container = self.container;
+        let codec = self.codec.unwrap_or_else(|| {
+            match probe.and_then(|p| p.video()).and_then(|v| v.codec.as_deref()) {
+                Some(video) if container.accepts_any_codec() || container.copyable_video().contains(&video) => {
+                    CodecPreset::Copy
+                }
+                _ => CodecPreset::H264,
+            }
+        });
+        let crf = self.profile.as_ref().and_then(|p| p.crf);
+        let preset = self.profile.as_ref().and_then(|p| p.preset.as_deref());
+
+        if codec == CodecPreset::Copy
+            && let Some(video) = probe.and_then(|p| p.video()).and_then(|v| v.codec.as_deref())
+            && !container.accepts_any_codec()
+            && !container.copyable_video().contains(&video)
+        {
+            return Err(format!(
+                "源视频编码 {} 无法直接复制到 {} 容器，请指定转码预设",
+                video,
+                container.extension()
+            ));
+        }
+        let mut args = codec.video_args(container, crf, preset);
+
+        let audio_codec = probe.map(|p| p.audio().and_then(|a| a.codec.clone()));
+        match (self.profile.as_ref().and_then(|p| p.audio_codec.clone()), audio_codec) {
+            // 探测确认没有音轨
+            (_, Some(None)) => args.push("-an".to_string()),
+            (Some(audio_codec), _) => {
+                args.extend(["-c:a".to_string(), audio_codec]);
+                if let Some(bitrate) = self.profile.as_ref().and_then(|p| p.audio_bitrate.clone()) {
+                    args.extend(["-b:a".to_string(), bitrate]);
+                }
+            }
+            (None, Some(Some(source)))
+                if container.accepts_any_codec() || container.copyable_audio().contains(&source.as_str()) =>
+            {
+                args.extend(["-c:a".to_string(), "copy".to_string()]);
+            }
+            (None, _) if container.accepts_any_codec() && codec == CodecPreset::Copy => {
+                args.extend(["-c:a".to_string(), "copy".to_string()]);
+            }
+            (None, _) => args.extend(container.default_audio_args()),
+        }
+
+        if let Some(profile) = &self.profile {
+            args.extend(profile.extra_args.iter().cloned());
+        }
+        if container.accepts_any_codec() && codec == CodecPreset::Copy {
+            // 归档时保留全部音视频流（多路视频、多音轨）
+            args.splice(0..0, [
+                "-map".to_string(), "0:v".to_string(),
+                "-map".to_string(), "0:a?".to_string(),
+            ]);
+        }
+        Ok(args)
+    }
+}
+
+/// 配置文件格式
+#[derive(Debug, Default, Deserialize)]
+struct EncodingsFile {
+    #[serde(default)]
+    encodings: Vec<EncodingProfile>,
+}
+
+/// 命名编码配置注册表
+///
+/// 内置 `web`、`browser-webm`、`archive` 三个配置，配置文件中的同名配置会覆盖内置配置
+#[derive(Debug, Clone)]
+pub struct EncodingRegistry {
+    profiles: Vec<EncodingProfile>,
+}
+
+impl EncodingRegistry {
+    /// 只包含内置配置
+    pub fn builtin() -> Self {
+        Self { profiles: EncodingProfile::builtin() }
+    }
+
+    /// 从配置文件加载，文件不存在时只使用内置配置
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !Path::new(path).exists() {
+            tracing::info!("Encoding profile file {} not found, using built-in encodings", path);
+            return Ok(Self::builtin());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("读取编码配置文件失败 {}: {}", path, e))?;
+        let file: EncodingsFile = serde_json::from_str(&content)
+            .map_err(|e| format!("解析编码配置文件失败 {}: {}", path, e))?;
+
+        let mut profiles: Vec<EncodingProfile> = Vec::new();
+        for profile in file.encodings {
+            if profile.name.is_empty() {
+                return Err(format!("编码配置缺少name: {}", path));
+            }
+            if profiles.iter().any(|p| p.name == profile.name) {
+                return Err(format!("编码配置重复: {}", profile.name));
+            }
+            profile.codec.check_container(profile.container)
+                .map_err(|e| format!("编码配置 {} 无效: {}", profile.name, e))?;
+            profiles.push(profile);
+        }
+
+        for builtin in EncodingProfile::builtin() {
+            if !profiles.iter().any(|p| p.name == builtin.name) {
+                profiles.push(builtin);
+            }
+        }
+
+        tracing::info!("Loaded {} encoding profiles from {}", profiles.len(), path);
+        Ok(Self { profiles })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EncodingProfile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn list(&self) -> &[EncodingProfile] {
+        &self.profiles
+    }
+
+    /// 合并请求的容器、编码预设和命名编码配置
+    pub fn resolve(&self, output: &ClipOutput) -> Result<ResolvedOutput, String> {
+        let profile = match &output.encoding {
+            Some(name) => Some(
+                self.get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("未知的编码配置: {}", name))?
+            ),
+            None => None,
+        };
+
+        let container = match output.package {
+            Some(_) if output.container.is_some() => {
+                return Err("container 与 package 不能同时指定".to_string());
+            }
+            Some(package) => package.segment_container(),
+            None => output.container
+                .or(profile.as_ref().map(|p| p.container))
+                .unwrap_or_default(),
+        };
+        let mut codec = output.codec.or(profile.as_ref().map(|p| p.codec));
+        // WebM不能沿用默认的H.264编码
+        if codec.is_none() && container == OutputContainer::Webm {
+            codec = Some(CodecPreset::Vp9);
+        }
+        // 遮挡、裁剪缩放和叠加滤镜不能与直接复制同时使用
+        if output.has_filters() {
+            match codec {
+                Some(CodecPreset::Copy) => {
+                    return Err("隐私遮挡、裁剪缩放、叠加文字或水印需要重新编码，不能使用 copy".to_string());
+                }
+                None => codec = Some(CodecPreset::H264),
+                Some(_) => {}
+            }
+        }
+        if let Some(codec) = codec {
+            codec.check_container(container)?;
+        }
+
+        Ok(ResolvedOutput { container, package: output.package, codec, profile })
+    }
+}
+
+impl Default for EncodingRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
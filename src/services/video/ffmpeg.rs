@@ -0,0 +1,292 @@
+use std::path::PathBuf;
+use std::process::{Output, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use crate::services::source::{redact_text, redact_url};
+
+/// ffmpeg `-progress` 输出的进度信息
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FfmpegProgress {
+    /// 已输出帧数
+    pub frame: u64,
+    /// 当前编码帧率
+    pub fps: f64,
+    /// 已输出的媒体时长（秒）
+    pub out_time: f64,
+    /// 处理速度，相对实时的倍数
+    pub speed: f64,
+}
+
+impl FfmpegProgress {
+    /// 解析一行 `key=value` 格式的进度输出
+    ///
+    /// 返回true表示一个进度块结束（遇到 `progress=` 行），可以对外发布
+    fn apply_line(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return false;
+        };
+
+        match key {
+            "frame" => {
+                if let Ok(frame) = value.parse() {
+                    self.frame = frame;
+                }
+            }
+            "fps" => {
+                if let Ok(fps) = value.parse() {
+                    self.fps = fps;
+                }
+            }
+            // out_time_ms 实际单位也是微秒，两者取其一即可
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.out_time = us.max(0) as f64 / 1_000_000.0;
+                }
+            }
+            "speed" => {
+                if let Ok(speed) = value.trim().trim_end_matches('x').parse() {
+                    self.speed = speed;
+                }
+            }
+            "progress" => return true,
+            _ => {}
+        }
+
+        false
+    }
+}
+
+/// 进程组守卫
+///
+/// ffmpeg在独立进程组中运行，守卫被丢弃时结束整个进程组，
+/// 保证请求取消、超时或客户端断开后不会遗留ffmpeg进程。
+struct ProcessGroupGuard {
+    pid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    fn new(pid: Option<u32>) -> Self {
+        Self { pid }
+    }
+
+    /// 进程已正常退出，无需再结束
+    fn disarm(&mut self) {
+        self.pid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        let Some(pid) = self.pid.take() else {
+            return;
+        };
+
+        tracing::warn!("Killing ffmpeg process group {}", pid);
+
+        #[cfg(unix)]
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+}
+
+/// 未完成输出守卫
+///
+/// 在输出确认完整之前持有，被丢弃时删除输出文件（或目录），
+/// 避免失败、超时或取消的任务在clips目录留下残缺文件。
+pub struct PartialOutputGuard {
+    path: Option<PathBuf>,
+}
+
+impl PartialOutputGuard {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: Some(path.into()) }
+    }
+
+    /// 输出已完整，保留文件
+    pub fn keep(&mut self) {
+        self.path = None;
+    }
+}
+
+impl Drop for PartialOutputGuard {
+    fn drop(&mut self) {
+        let Some(path) = self.path.take() else {
+            return;
+        };
+
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+
+        match result {
+            Ok(()) => tracing::info!("Removed partial output: {}", path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to remove partial output {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// 进度回调
+pub type ProgressCallback = Arc<dyn Fn(&FfmpegProgress) + Send + Sync>;
+
+/// ffmpeg命令执行器
+///
+/// 以子进程方式异步运行ffmpeg，通过 `-progress pipe:1` 实时解析进度，
+/// 超时或 `run` 返回的future被丢弃（任务取消、客户端断开）时结束子进程。
+///
+/// # Example
+/// ```rust
+/// let output = FfmpegCommand::new(args)
+///     .timeout(Duration::from_secs(30))
+///     .on_progress(callback)
+///     .run()
+///     .await?;
+/// ```
+pub struct FfmpegCommand {
+    program: &'static str,
+    args: Vec<String>,
+    timeout: Duration,
+    on_progress: Option<ProgressCallback>,
+    envs: Vec<(String, String)>,
+}
+
+impl FfmpegCommand {
+    /// 创建ffmpeg命令，默认超时30秒
+    pub fn new(args: Vec<String>) -> Self {
+        Self {
+            program: "ffmpeg",
+            args,
+            timeout: Duration::from_secs(30),
+            on_progress: None,
+            envs: Vec::new(),
+        }
+    }
+
+    /// 创建ffprobe命令，stdout作为输出返回，不解析进度
+    pub fn ffprobe(args: Vec<String>) -> Self {
+        Self {
+            program: "ffprobe",
+            ..Self::new(args)
+        }
+    }
+
+    fn reports_progress(&self) -> bool {
+        self.program == "ffmpeg"
+    }
+
+    /// 设置超时时间
+    pub fn timeout(mut self, limit: Duration) -> Self {
+        self.timeout = limit;
+        self
+    }
+
+    /// 设置子进程的环境变量
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.envs.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// 设置进度回调
+    pub fn on_progress(mut self, callback: Option<ProgressCallback>) -> Self {
+        self.on_progress = callback;
+        self
+    }
+
+    /// 执行命令，返回退出状态和输出
+    ///
+    /// ffmpeg的stdout被进度信息占用，返回的stdout始终为空；ffprobe返回完整stdout。
+    /// 返回的stderr中URL的凭据已被去掉
+    pub async fn run(self) -> Result<Output, String> {
+        let reports_progress = self.reports_progress();
+        let mut cmd = Command::new(self.program);
+        if reports_progress {
+            cmd.args(["-nostdin", "-nostats", "-progress", "pipe:1"]);
+        }
+        cmd.args(&self.args)
+            .envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        // 日志中去除URL里的凭据
+        let logged_args: Vec<String> = self.args.iter().map(|arg| redact_url(arg)).collect();
+        tracing::info!("Executing ffmpeg command: {} {}", self.program, logged_args.join(" "));
+
+        let mut child = cmd.spawn().map_err(|e| {
+            tracing::error!("FFmpeg execution error: {}", e);
+            format!("Failed to execute {}: {}", self.program, e)
+        })?;
+        let mut group_guard = ProcessGroupGuard::new(child.id());
+
+        let stdout = child.stdout.take().ok_or("Failed to capture ffmpeg stdout")?;
+        let mut stderr = child.stderr.take().ok_or("Failed to capture ffmpeg stderr")?;
+
+        // stderr单独读取，避免管道写满阻塞ffmpeg
+        let stderr_task = tokio::spawn(async move {
+            let mut buf = Vec::new();
+            let _ = stderr.read_to_end(&mut buf).await;
+            buf
+        });
+
+        let on_progress = self.on_progress;
+        let mut stdout_buf = Vec::new();
+        let work = async {
+            if reports_progress {
+                let mut lines = BufReader::new(stdout).lines();
+                let mut progress = FfmpegProgress::default();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if progress.apply_line(&line)
+                        && let Some(callback) = &on_progress
+                    {
+                        callback(&progress);
+                    }
+                }
+            } else {
+                let mut stdout = stdout;
+                let _ = stdout.read_to_end(&mut stdout_buf).await;
+            }
+            child.wait().await
+        };
+
+        let result = timeout(self.timeout, work).await;
+        let status = match result {
+            Ok(Ok(status)) => {
+                group_guard.disarm();
+                status
+            }
+            Ok(Err(e)) => {
+                tracing::error!("FFmpeg wait error: {}", e);
+                return Err(format!("Failed to wait for ffmpeg: {}", e));
+            }
+            Err(_) => {
+                drop(group_guard);
+                let _ = child.kill().await;
+                tracing::error!("FFmpeg command timeout after {} seconds", self.timeout.as_secs());
+                return Err(format!("FFmpeg command timeout after {} seconds", self.timeout.as_secs()));
+            }
+        };
+
+        // stderr会被写入日志、任务信息和错误响应，先去掉拉流地址中的凭据
+        let stderr = stderr_task.await.unwrap_or_default();
+        let stderr = redact_text(&String::from_utf8_lossy(&stderr)).into_bytes();
+        tracing::info!("FFmpeg command completed");
+
+        Ok(Output {
+            status,
+            stdout: stdout_buf,
+            stderr,
+        })
+    }
+}
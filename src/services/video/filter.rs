@@ -0,0 +1,72 @@
+/// 单输入单输出的视频滤镜图
+///
+/// 简单滤镜追加到当前链上；遮挡、水印这类需要分支或额外输入的步骤先用 `stream`
+/// 结束当前链，再用标签把各段连接起来，最后输出到指定标签。
+pub(super) struct FilterGraph {
+    /// 标签前缀，同一个 `-filter_complex` 中的多个滤镜图不会重名
+    scope: String,
+    chains: Vec<String>,
+    /// 当前视频流的标签
+    current: String,
+    /// 尚未结束的简单滤镜
+    pending: Vec<String>,
+    next_label: usize,
+}
+
+impl FilterGraph {
+    /// `input` 为输入视频流的标签，`-vf` 用 `in`，`-filter_complex` 用 `0:v` 这类输入流
+    pub fn new(input: &str) -> Self {
+        Self {
+            scope: input.chars().filter(|c| c.is_ascii_alphanumeric()).collect(),
+            chains: Vec::new(),
+            current: input.to_string(),
+            pending: Vec::new(),
+            next_label: 0,
+        }
+    }
+
+    /// 在当前链上追加一个简单滤镜
+    pub fn push(&mut self, filter: impl Into<String>) {
+        self.pending.push(filter.into());
+    }
+
+    /// 生成不重复的标签
+    pub fn label(&mut self, name: &str) -> String {
+        self.next_label += 1;
+        format!("{}_{}{}", self.scope, name, self.next_label)
+    }
+
+    /// 结束当前链，返回当前视频流的标签
+    pub fn stream(&mut self) -> String {
+        if !self.pending.is_empty() {
+            let label = self.label("s");
+            self.chains.push(format!("[{}]{}[{}]", self.current, self.pending.join(","), label));
+            self.pending.clear();
+            self.current = label;
+        }
+        self.current.clone()
+    }
+
+    /// 追加一段带标签的滤镜链，`output` 为该段输出的新视频流
+    pub fn add_chain(&mut self, chain: String, output: Option<String>) {
+        self.chains.push(chain);
+        if let Some(output) = output {
+            self.current = output;
+        }
+    }
+
+    /// 输出到指定标签，返回完整滤镜图
+    pub fn finish(mut self, output: &str) -> String {
+        let pending = if self.pending.is_empty() { "null".to_string() } else { self.pending.join(",") };
+        self.chains.push(format!("[{}]{}[{}]", self.current, pending, output));
+        self.chains.join(";")
+    }
+
+    /// 作为 `-vf` 参数，没有任何滤镜时返回None，只有简单滤镜时返回逗号连接的滤镜链
+    pub fn into_vf(self) -> Option<String> {
+        if self.chains.is_empty() {
+            return (!self.pending.is_empty()).then(|| self.pending.join(","));
+        }
+        Some(self.finish("out"))
+    }
+}
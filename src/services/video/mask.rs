@@ -0,0 +1,179 @@
+use std::path::Path;
+use image::{GrayImage, Luma};
+use serde::{Deserialize, Serialize};
+
+use super::{escape_filter_path, valid_color, FilterGraph};
+
+/// 遮挡蒙版的栅格尺寸，使用时拉伸到实际画面大小
+const MASK_WIDTH: u32 = 1920;
+const MASK_HEIGHT: u32 = 1080;
+/// 单个画面最多的遮挡区域数量
+const MAX_MASKS: usize = 32;
+/// 多边形最多的顶点数量
+const MAX_POLYGON_POINTS: usize = 64;
+
+/// 遮挡区域，坐标是相对画面宽高的比例（0-1），与分辨率无关，主码流和子码流通用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "lowercase")]
+pub enum MaskRegion {
+    Rect { x: f64, y: f64, width: f64, height: f64 },
+    /// 顶点按顺序连接，自动闭合
+    Polygon { points: Vec<[f64; 2]> },
+}
+
+/// 遮挡方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaskStyle {
+    /// 纯色填充，最安全，默认
+    #[default]
+    Fill,
+    Blur,
+    Pixelate,
+}
+
+/// 隐私遮挡
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyMask {
+    #[serde(flatten)]
+    pub region: MaskRegion,
+    #[serde(default)]
+    pub style: MaskStyle,
+    /// 填充颜色，只对fill生效，默认black
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl MaskStyle {
+    /// 对整个画面施加的效果，再按蒙版合成到原画面
+    fn effect(&self, color: &str) -> String {
+        match self {
+            Self::Fill => format!("drawbox=x=0:y=0:w=iw:h=ih:color={}:t=fill", color),
+            Self::Blur => {
+                "boxblur=luma_radius='min(w,h)/20':luma_power=3:chroma_radius='min(cw,ch)/20':chroma_power=3".to_string()
+            }
+            Self::Pixelate => "pixelize=width=16:height=16".to_string(),
+        }
+    }
+}
+
+impl PrivacyMask {
+    /// 校验坐标范围和颜色
+    pub fn validate(&self) -> Result<(), String> {
+        let in_range = |v: f64| v.is_finite() && (0.0..=1.0).contains(&v);
+        match &self.region {
+            MaskRegion::Rect { x, y, width, height } => {
+                if !in_range(*x) || !in_range(*y) || !in_range(*width) || !in_range(*height) {
+                    return Err("遮挡区域坐标必须是 0-1 之间的比例".to_string());
+                }
+                if *width <= 0.0 || *height <= 0.0 || x + width > 1.0 || y + height > 1.0 {
+                    return Err("遮挡区域必须在画面范围内且宽高大于0".to_string());
+                }
+            }
+            MaskRegion::Polygon { points } => {
+                if !(3..=MAX_POLYGON_POINTS).contains(&points.len()) {
+                    return Err(format!("多边形顶点数量必须在 3-{} 之间", MAX_POLYGON_POINTS));
+                }
+                if points.iter().any(|[x, y]| !in_range(*x) || !in_range(*y)) {
+                    return Err("遮挡区域坐标必须是 0-1 之间的比例".to_string());
+                }
+            }
+        }
+        if let Some(color) = &self.color {
+            if self.style != MaskStyle::Fill {
+                return Err("color 只对 fill 遮挡生效".to_string());
+            }
+            if !valid_color(color) {
+                return Err(format!("无效的颜色: {}", color));
+            }
+        }
+        Ok(())
+    }
+
+    /// 把遮挡区域画到蒙版上，边缘向外取整，宁可多遮不可漏遮
+    fn rasterize(&self, mask: &mut GrayImage) {
+        let (w, h) = (mask.width() as f64, mask.height() as f64);
+        match &self.region {
+            MaskRegion::Rect { x, y, width, height } => {
+                let x0 = (x * w).floor() as u32;
+                let y0 = (y * h).floor() as u32;
+                let x1 = (((x + width) * w).ceil() as u32).min(mask.width());
+                let y1 = (((y + height) * h).ceil() as u32).min(mask.height());
+                for py in y0..y1 {
+                    for px in x0..x1 {
+                        mask.put_pixel(px, py, Luma([255]));
+                    }
+                }
+            }
+            MaskRegion::Polygon { points } => {
+                let points: Vec<(f64, f64)> = points.iter().map(|[x, y]| (x * w, y * h)).collect();
+                // 扫描线填充（奇偶规则），按像素中心求交点
+                for py in 0..mask.height() {
+                    let cy = py as f64 + 0.5;
+                    let mut crossings: Vec<f64> = Vec::new();
+                    for (i, &(x0, y0)) in points.iter().enumerate() {
+                        let (x1, y1) = points[(i + 1) % points.len()];
+                        if (y0 <= cy && cy < y1) || (y1 <= cy && cy < y0) {
+                            crossings.push(x0 + (cy - y0) / (y1 - y0) * (x1 - x0));
+                        }
+                    }
+                    crossings.sort_by(|a, b| a.total_cmp(b));
+                    for pair in crossings.chunks_exact(2) {
+                        let start = pair[0].floor().max(0.0) as u32;
+                        let end = (pair[1].ceil() as u32).min(mask.width());
+                        for px in start..end {
+                            mask.put_pixel(px, py, Luma([255]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 校验遮挡列表
+pub fn validate_masks(masks: &[PrivacyMask]) -> Result<(), String> {
+    if masks.len() > MAX_MASKS {
+        return Err(format!("最多 {} 个遮挡区域", MAX_MASKS));
+    }
+    masks.iter().try_for_each(PrivacyMask::validate)
+}
+
+/// 在滤镜图上追加隐私遮挡，必须在缩放、裁剪之前执行
+///
+/// 相同方式和颜色的区域画到同一张蒙版PNG（写入 `work_dir`），
+/// 整个画面施加效果后用蒙版作为透明度合成回原画面
+pub(super) fn apply_masks(masks: &[PrivacyMask], graph: &mut FilterGraph, work_dir: &Path) -> Result<(), String> {
+    let mut groups: Vec<(MaskStyle, &str, GrayImage)> = Vec::new();
+    for mask in masks {
+        let color = mask.color.as_deref().unwrap_or("black");
+        let index = match groups.iter().position(|(style, c, _)| *style == mask.style && *c == color) {
+            Some(index) => index,
+            None => {
+                groups.push((mask.style, color, GrayImage::new(MASK_WIDTH, MASK_HEIGHT)));
+                groups.len() - 1
+            }
+        };
+        mask.rasterize(&mut groups[index].2);
+    }
+
+    for (i, (style, color, image)) in groups.into_iter().enumerate() {
+        let path = work_dir.join(format!("mask_{}.png", i));
+        image.save(&path)
+            .map_err(|e| format!("Failed to write privacy mask: {}", e))?;
+
+        let base = graph.stream();
+        let [original, effect_in, effect, mask, scaled_mask, scaled_effect, masked, out] =
+            ["mo", "me", "mf", "mk", "mks", "mfs", "mfa", "mout"].map(|name| graph.label(name));
+        graph.add_chain(format!("movie={},format=gray[{}]", escape_filter_path(&path), mask), None);
+        graph.add_chain(format!("[{}]split[{}][{}]", base, original, effect_in), None);
+        graph.add_chain(format!("[{}]{}[{}]", effect_in, style.effect(color), effect), None);
+        graph.add_chain(
+            format!("[{}][{}]scale2ref=w=rw:h=rh[{}][{}]", mask, effect, scaled_mask, scaled_effect),
+            None,
+        );
+        graph.add_chain(format!("[{}][{}]alphamerge[{}]", scaled_effect, scaled_mask, masked), None);
+        graph.add_chain(format!("[{}][{}]overlay[{}]", original, masked, out), Some(out));
+    }
+    Ok(())
+}
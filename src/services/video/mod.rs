@@ -1,5 +1,36 @@
+pub mod archive;
+pub mod audio;
+pub mod concat;
+pub mod crop;
+pub mod encoding;
+pub mod ffmpeg;
+mod filter;
+pub mod mask;
+pub mod mosaic;
+pub mod overlay;
+pub mod preview;
+pub mod probe;
+pub mod profile;
+pub mod record;
 pub mod snapshot;
+pub mod snapshot_options;
+pub mod sprite;
 pub mod stream_handler;
  
+use filter::FilterGraph;
+
+pub use audio::*;
+pub use concat::*;
+pub use crop::*;
+pub use encoding::*;
+pub use ffmpeg::*;
+pub use mask::*;
+pub use mosaic::*;
+pub use overlay::*;
+pub use preview::*;
+pub use probe::*;
+pub use profile::*;
 pub use snapshot::*;
-pub use stream_handler::*; 
\ No newline at end of file
+pub use snapshot_options::*;
+pub use sprite::*;
+pub use stream_handler::*;
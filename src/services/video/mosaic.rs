@@ -0,0 +1,202 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{apply_masks, escape_filter_path, FfmpegCommand, FilterGraph, PartialOutputGuard, PrivacyMask, ProgressCallback, VideoSnapshotService};
+
+/// 画面拼接布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MosaicLayout {
+    /// 左右并排
+    #[serde(rename = "1x2")]
+    SideBySide,
+    #[serde(rename = "2x2")]
+    Grid2x2,
+    #[serde(rename = "3x3")]
+    Grid3x3,
+}
+
+impl MosaicLayout {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "1x2" | "side-by-side" => Ok(Self::SideBySide),
+            "2x2" => Ok(Self::Grid2x2),
+            "3x3" => Ok(Self::Grid3x3),
+            _ => Err(format!("不支持的布局: {}，可选 1x2/2x2/3x3", name)),
+        }
+    }
+
+    /// (行数, 列数)
+    pub fn grid(&self) -> (u32, u32) {
+        match self {
+            Self::SideBySide => (1, 2),
+            Self::Grid2x2 => (2, 2),
+            Self::Grid3x3 => (3, 3),
+        }
+    }
+
+    /// 最多容纳的画面数
+    pub fn capacity(&self) -> usize {
+        let (rows, cols) = self.grid();
+        (rows * cols) as usize
+    }
+}
+
+/// 拼接输出参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MosaicOptions {
+    pub layout: MosaicLayout,
+    /// 输出宽度，各格平分
+    pub width: u32,
+    /// 输出高度，各格平分
+    pub height: u32,
+    pub fps: u32,
+    /// 右上角叠加服务器时间
+    pub timestamp: bool,
+}
+
+/// 拼接的一路输入
+#[derive(Debug, Clone)]
+pub struct MosaicInput {
+    /// 带凭据的拉流地址
+    pub url: String,
+    pub profile: Option<String>,
+    /// 格内左上角显示的名称
+    pub label: Option<String>,
+    /// 视频源登记的隐私遮挡
+    pub masks: Vec<PrivacyMask>,
+}
+
+/// 保持为偶数，yuv420p要求宽高为偶数
+fn even(value: u32) -> u32 {
+    value & !1
+}
+
+impl VideoSnapshotService {
+    /// 同时拉取多路视频流拼接为一个网格画面，保存到clips目录，返回文件名
+    ///
+    /// 每路输入使用各自流处理器的协议参数，画面等比缩放到格内，空格填黑
+    pub async fn compose_mosaic(
+        &self,
+        inputs: &[MosaicInput],
+        duration: f64,
+        options: &MosaicOptions,
+        on_progress: Option<ProgressCallback>,
+    ) -> Result<String, String> {
+        tracing::info!("Starting compose_mosaic with {} inputs, layout: {:?}", inputs.len(), options.layout);
+
+        let (rows, cols) = options.layout.grid();
+        let tile_w = even(options.width / cols);
+        let tile_h = even(options.height / rows);
+
+        // 标签和遮挡蒙版写入临时目录，标签用文件引用以避免在滤镜中转义任意字符
+        let temp_dir = tempfile::tempdir()
+            .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+
+        let mut args = Vec::new();
+        let mut margin = Duration::ZERO;
+        let mut filters = Vec::new();
+        for (i, input) in inputs.iter().enumerate() {
+            let handler = self.resolve_handler(&input.url, input.profile.as_deref())?;
+            margin = margin.max(handler.clip_timeout_margin());
+
+            // 输入端 -t，实时流读够时长即停止
+            args.extend(handler.input_args(&input.url));
+            args.extend([
+                "-t".to_string(), duration.to_string(),
+                "-i".to_string(), input.url.clone(),
+            ]);
+
+            // 遮挡坐标相对原画面，必须在缩放补边之前执行
+            let mut graph = FilterGraph::new(&format!("{}:v", i));
+            graph.push("setpts=PTS-STARTPTS");
+            let mask_dir = temp_dir.path().join(format!("input_{}", i));
+            std::fs::create_dir_all(&mask_dir)
+                .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+            apply_masks(&input.masks, &mut graph, &mask_dir)?;
+            graph.push(format!(
+                "fps={fps},scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1",
+                fps = options.fps,
+                w = tile_w,
+                h = tile_h
+            ));
+            if let Some(label) = &input.label {
+                let label_path = temp_dir.path().join(format!("label_{}.txt", i));
+                std::fs::write(&label_path, label)
+                    .map_err(|e| format!("Failed to write label: {}", e))?;
+                graph.push(format!(
+                    "drawtext=textfile={}:expansion=none:x=10:y=10:fontsize=24:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=6",
+                    escape_filter_path(&label_path)
+                ));
+            }
+            filters.push(graph.finish(&format!("v{}", i)));
+        }
+
+        // xstack的输出尺寸由各格位置决定，画面不足时用pad补齐整个网格
+        let pad = format!("pad={}:{}:0:0:black", tile_w * cols, tile_h * rows);
+        let mut grid = if inputs.len() == 1 {
+            // xstack至少需要两路输入
+            format!("[v0]{}", pad)
+        } else {
+            let layout: Vec<String> = (0..inputs.len() as u32)
+                .map(|i| format!("{}_{}", (i % cols) * tile_w, (i / cols) * tile_h))
+                .collect();
+            let stack_inputs: String = (0..inputs.len()).map(|i| format!("[v{}]", i)).collect();
+            format!(
+                "{}xstack=inputs={}:layout={}:fill=black,{}",
+                stack_inputs,
+                inputs.len(),
+                layout.join("|"),
+                pad
+            )
+        };
+        if options.timestamp {
+            grid.push_str(
+                ",drawtext=text='%{localtime\\:%Y-%m-%d %T}':x=w-tw-10:y=10:fontsize=24:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=6",
+            );
+        }
+        grid.push_str("[out]");
+        filters.push(grid);
+
+        let filename = format!("{}.mp4", Uuid::new_v4());
+        let output_path = self.clip_path(&filename);
+
+        args.extend([
+            "-filter_complex".to_string(), filters.join(";"),
+            "-map".to_string(), "[out]".to_string(),
+            "-t".to_string(), duration.to_string(),
+            "-an".to_string(),
+            "-c:v".to_string(), "libx264".to_string(),
+            "-preset".to_string(), "fast".to_string(),
+            "-crf".to_string(), "23".to_string(),
+            "-pix_fmt".to_string(), "yuv420p".to_string(),
+            "-movflags".to_string(), "+faststart".to_string(),
+            "-y".to_string(),
+            output_path.clone(),
+        ]);
+
+        let mut partial_output = PartialOutputGuard::new(&output_path);
+
+        let output = FfmpegCommand::new(args)
+            .timeout(margin + Duration::from_secs_f64(duration))
+            .on_progress(on_progress)
+            .run()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::error!("FFmpeg mosaic failed - stderr: {}", stderr);
+            return Err(format!("FFmpeg mosaic failed: {}", stderr));
+        }
+
+        match std::fs::metadata(&output_path) {
+            Ok(metadata) if metadata.len() > 0 => {
+                tracing::info!("Successfully created mosaic: {}, size: {} bytes", filename, metadata.len());
+            }
+            _ => return Err("Generated mosaic file is empty".to_string()),
+        }
+
+        partial_output.keep();
+        Ok(filename)
+    }
+}
@@ -0,0 +1,249 @@
+use std::path::{Component, Path};
+use serde::{Deserialize, Serialize};
+
+use super::FilterGraph;
+
+/// 文字和水印离画面边缘的距离（像素）
+const MARGIN: u32 = 10;
+/// 同一位置多行文字之间的间距（像素）
+const LINE_SPACING: u32 = 6;
+
+/// 叠加位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlayPosition {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl OverlayPosition {
+    /// drawtext的坐标表达式，`offset` 为同一位置前面各行占用的高度
+    fn text_xy(&self, offset: u32) -> String {
+        match self {
+            Self::TopLeft => format!("x={m}:y={}", MARGIN + offset, m = MARGIN),
+            Self::TopRight => format!("x=w-tw-{m}:y={}", MARGIN + offset, m = MARGIN),
+            Self::BottomLeft => format!("x={m}:y=h-th-{}", MARGIN + offset, m = MARGIN),
+            Self::BottomRight => format!("x=w-tw-{m}:y=h-th-{}", MARGIN + offset, m = MARGIN),
+            Self::Center => format!("x=(w-tw)/2:y=(h-th)/2+{}", offset),
+        }
+    }
+
+    /// overlay滤镜的坐标表达式
+    fn overlay_xy(&self) -> String {
+        match self {
+            Self::TopLeft => format!("x={m}:y={m}", m = MARGIN),
+            Self::TopRight => format!("x=W-w-{m}:y={m}", m = MARGIN),
+            Self::BottomLeft => format!("x={m}:y=H-h-{m}", m = MARGIN),
+            Self::BottomRight => format!("x=W-w-{m}:y=H-h-{m}", m = MARGIN),
+            Self::Center => "x=(W-w)/2:y=(H-h)/2".to_string(),
+        }
+    }
+}
+
+/// 文字叠加
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextOverlay {
+    /// 文字内容，`timestamp` 为true时按strftime模板渲染为UTC时间，例如 `%Y-%m-%d %H:%M:%S`
+    pub text: String,
+    #[serde(default)]
+    pub timestamp: bool,
+    #[serde(default)]
+    pub position: OverlayPosition,
+    /// 字号，默认24
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<u32>,
+    /// 颜色名称或 `#RRGGBB`，可带 `@透明度`，默认white
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// PNG图片水印
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatermarkOverlay {
+    /// 水印目录下的文件名
+    pub file: String,
+    #[serde(default = "default_watermark_position")]
+    pub position: OverlayPosition,
+    /// 水印宽度，默认保持原尺寸
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// 不透明度 0-1，默认1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f32>,
+}
+
+fn default_watermark_position() -> OverlayPosition {
+    OverlayPosition::BottomRight
+}
+
+/// 截图和剪辑的叠加内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OverlaySpec {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text: Vec<TextOverlay>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<WatermarkOverlay>,
+    /// 时间叠加的起点（Unix秒），录像剪辑时为第一帧的录制时间，逐帧加上画面时间戳渲染；
+    /// 为None时渲染处理该帧时的当前时间
+    #[serde(skip)]
+    pub time_base: Option<f64>,
+}
+
+/// 颜色只允许字母、数字和 `#@.`，防止注入滤镜参数
+pub(super) fn valid_color(color: &str) -> bool {
+    !color.is_empty()
+        && color.len() <= 32
+        && color.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '#' | '@' | '.'))
+}
+
+/// 转义滤镜参数中的文件路径，结果直接作为参数值，不再加引号
+///
+/// 先按滤镜选项转义，再按滤镜图转义
+pub(super) fn escape_filter_path(path: &Path) -> String {
+    let mut option = String::new();
+    for c in path.to_string_lossy().chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+    let mut graph = String::new();
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+/// 转义drawtext展开函数的参数
+fn escape_expansion_arg(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '\\' | '\'' | ':' | '}') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl OverlaySpec {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.watermark.is_none()
+    }
+
+    /// 校验参数范围
+    pub fn validate(&self) -> Result<(), String> {
+        if self.text.len() > 10 {
+            return Err("最多叠加 10 行文字".to_string());
+        }
+        for text in &self.text {
+            if text.text.is_empty() || text.text.len() > 256 {
+                return Err("叠加文字长度必须在 1-256 之间".to_string());
+            }
+            if let Some(size) = text.font_size
+                && !(8..=200).contains(&size)
+            {
+                return Err("font_size 必须在 8-200 之间".to_string());
+            }
+            if let Some(color) = &text.color
+                && !valid_color(color)
+            {
+                return Err(format!("无效的颜色: {}", color));
+            }
+        }
+        if let Some(watermark) = &self.watermark {
+            // 只允许水印目录下的文件名，不能跳出目录
+            let path = Path::new(&watermark.file);
+            let mut components = path.components();
+            if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+                return Err(format!("无效的水印文件名: {}", watermark.file));
+            }
+            if !watermark.file.to_ascii_lowercase().ends_with(".png") {
+                return Err("水印只支持 PNG 图片".to_string());
+            }
+            if let Some(width) = watermark.width
+                && !(1..=4096).contains(&width)
+            {
+                return Err("水印 width 必须在 1-4096 之间".to_string());
+            }
+            if let Some(opacity) = watermark.opacity
+                && !(0.0..=1.0).contains(&opacity)
+            {
+                return Err("水印 opacity 必须在 0-1 之间".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// 在滤镜图上追加文字和水印
+    ///
+    /// 文字写入 `work_dir` 下的文件再用 `textfile` 引用，避免转义任意字符；
+    /// 水印用 `movie` 源读入，所以整个滤镜图仍然只有一个输入和一个输出
+    pub(super) fn apply(
+        &self,
+        graph: &mut FilterGraph,
+        work_dir: &Path,
+        watermark_dir: &Path,
+    ) -> Result<(), String> {
+        let mut offsets = [0u32; 5];
+        for (i, text) in self.text.iter().enumerate() {
+            let font_size = text.font_size.unwrap_or(24);
+            let content = if text.timestamp {
+                let format = escape_expansion_arg(&text.text);
+                match self.time_base {
+                    Some(base) => format!("%{{pts:gmtime:{}:{}}}", base, format),
+                    None => format!("%{{gmtime:{}}}", format),
+                }
+            } else {
+                text.text.clone()
+            };
+            let text_path = work_dir.join(format!("overlay_{}.txt", i));
+            std::fs::write(&text_path, content)
+                .map_err(|e| format!("Failed to write overlay text: {}", e))?;
+
+            let offset = &mut offsets[text.position as usize];
+            graph.push(format!(
+                "drawtext=textfile={}:expansion={}:{}:fontsize={}:fontcolor={}:box=1:boxcolor=black@0.4:boxborderw=4",
+                escape_filter_path(&text_path),
+                if text.timestamp { "normal" } else { "none" },
+                text.position.text_xy(*offset),
+                font_size,
+                text.color.as_deref().unwrap_or("white"),
+            ));
+            *offset += font_size + LINE_SPACING * 2;
+        }
+
+        let Some(watermark) = &self.watermark else {
+            return Ok(());
+        };
+
+        let path = watermark_dir.join(&watermark.file);
+        if !path.is_file() {
+            return Err(format!("水印文件不存在: {}", watermark.file));
+        }
+        let mut source = format!("movie={},format=rgba", escape_filter_path(&path));
+        if let Some(width) = watermark.width {
+            source.push_str(&format!(",scale={}:-1", width));
+        }
+        if let Some(opacity) = watermark.opacity {
+            source.push_str(&format!(",colorchannelmixer=aa={}", opacity));
+        }
+
+        let base = graph.stream();
+        let wm = graph.label("wm");
+        let out = graph.label("wmo");
+        graph.add_chain(format!("{}[{}]", source, wm), None);
+        graph.add_chain(
+            format!("[{}][{}]overlay={}[{}]", base, wm, watermark.position.overlay_xy(), out),
+            Some(out),
+        );
+        Ok(())
+    }
+}
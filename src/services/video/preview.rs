@@ -0,0 +1,147 @@
+use std::path::Path;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::services::source::redact_url;
+use super::{apply_masks, FfmpegCommand, FilterGraph, PartialOutputGuard, PrivacyMask, VideoSnapshotService};
+
+/// 动图格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreviewFormat {
+    #[default]
+    Gif,
+    Webp,
+}
+
+impl PreviewFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "gif" => Ok(Self::Gif),
+            "webp" => Ok(Self::Webp),
+            _ => Err(format!("不支持的动图格式: {}，可选 gif/webp", name)),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// 动图参数
+#[derive(Debug, Clone)]
+pub struct PreviewOptions {
+    pub format: PreviewFormat,
+    /// 帧率
+    pub fps: u32,
+    /// 宽度，高度按视频比例计算
+    pub width: u32,
+    /// 视频源登记的隐私遮挡
+    pub masks: Vec<PrivacyMask>,
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self {
+            format: PreviewFormat::Gif,
+            fps: 10,
+            width: 320,
+            masks: Vec::new(),
+        }
+    }
+}
+
+impl PreviewOptions {
+    /// 编码参数，替代剪辑的 `-c:v`/`-c:a` 部分
+    ///
+    /// GIF在同一条命令中先用 palettegen 生成调色板再用 paletteuse 映射，
+    /// 比默认的256色通用调色板清晰得多；WebP是真彩色，直接有损编码。
+    /// 遮挡蒙版写入 `work_dir`
+    fn codec_args(&self, work_dir: &Path) -> Result<Vec<String>, String> {
+        let mut graph = FilterGraph::new(match self.format {
+            PreviewFormat::Gif => "0:v",
+            PreviewFormat::Webp => "in",
+        });
+        apply_masks(&self.masks, &mut graph, work_dir)?;
+        graph.push(format!("fps={},scale={}:-2:flags=lanczos", self.fps, self.width));
+
+        let mut args = match self.format {
+            PreviewFormat::Gif => vec![
+                "-filter_complex".to_string(),
+                format!(
+                    "{};[scaled]split[a][b];[a]palettegen=stats_mode=diff[p];[b][p]paletteuse=dither=bayer:bayer_scale=5:diff_mode=rectangle",
+                    graph.finish("scaled")
+                ),
+                "-f".to_string(), "gif".to_string(),
+            ],
+            PreviewFormat::Webp => vec![
+                "-vf".to_string(), graph.into_vf().unwrap_or_default(),
+                "-c:v".to_string(), "libwebp".to_string(),
+                "-lossless".to_string(), "0".to_string(),
+                "-quality".to_string(), "70".to_string(),
+                "-compression_level".to_string(), "4".to_string(),
+                "-f".to_string(), "webp".to_string(),
+            ],
+        };
+        args.extend([
+            "-loop".to_string(), "0".to_string(),
+            "-an".to_string(),
+        ]);
+        Ok(args)
+    }
+}
+
+impl VideoSnapshotService {
+    /// 截取视频流一段生成动图，保存到clips目录，返回文件名
+    pub async fn create_preview(
+        &self,
+        url: &str,
+        start: f64,
+        duration: f64,
+        profile: Option<&str>,
+        options: &PreviewOptions,
+    ) -> Result<String, String> {
+        tracing::info!("Starting create_preview for URL: {}, start: {}, duration: {}", redact_url(url), start, duration);
+
+        let filename = format!("{}.{}", Uuid::new_v4(), options.format.extension());
+        let output_path = self.clip_path(&filename);
+
+        let work_dir = tempfile::tempdir()
+            .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+        let handler = self.resolve_handler(url, profile)?;
+        let args = handler.build_clip_args(url, start, duration, options.codec_args(work_dir.path())?, &output_path);
+
+        let mut partial_output = PartialOutputGuard::new(&output_path);
+
+        let limit = handler.clip_timeout_margin() + Duration::from_secs_f64(duration);
+        let output = FfmpegCommand::new(args)
+            .timeout(limit)
+            .run()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::error!("FFmpeg preview failed - stderr: {}", stderr);
+            return Err(format!("FFmpeg preview failed: {}", stderr));
+        }
+
+        match std::fs::metadata(&output_path) {
+            Ok(metadata) if metadata.len() > 0 => {
+                tracing::info!("Successfully created preview: {}, size: {} bytes", filename, metadata.len());
+            }
+            _ => return Err("Generated preview file is empty".to_string()),
+        }
+
+        partial_output.keep();
+        Ok(filename)
+    }
+}
@@ -0,0 +1,167 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// MP4容器可以直接复制的视频编码
+const MP4_COPYABLE_VIDEO: &[&str] = &["h264", "hevc", "mpeg4", "av1"];
+
+/// MP4容器可以直接复制的音频编码
+const MP4_COPYABLE_AUDIO: &[&str] = &["aac", "mp3"];
+
+/// ffprobe探测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    /// 容器格式，例如 `rtsp`、`mov,mp4,m4a,3gp,3g2,mj2`
+    pub container: Option<String>,
+    /// 时长（秒），实时流通常没有
+    pub duration: Option<f64>,
+    /// 总码率（bit/s）
+    pub bit_rate: Option<u64>,
+    pub streams: Vec<ProbeStream>,
+}
+
+/// 单个流的信息
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeStream {
+    pub index: u32,
+    /// `video`、`audio`、`subtitle`、`data`
+    pub codec_type: String,
+    pub codec: Option<String>,
+    pub profile: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 帧率（fps）
+    pub frame_rate: Option<f64>,
+    pub pixel_format: Option<String>,
+    pub bit_rate: Option<u64>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub sample_rate: Option<u32>,
+}
+
+impl ProbeResult {
+    /// 解析 `ffprobe -print_format json -show_format -show_streams` 的输出
+    pub fn from_ffprobe_json(data: &[u8]) -> Result<Self, String> {
+        let root: Value = serde_json::from_slice(data)
+            .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+
+        let format = &root["format"];
+        let streams = root["streams"]
+            .as_array()
+            .map(|streams| streams.iter().map(ProbeStream::from_json).collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            container: str_field(format, "format_name"),
+            duration: num_field(format, "duration"),
+            bit_rate: num_field(format, "bit_rate"),
+            streams,
+        })
+    }
+
+    /// 第一个视频流
+    pub fn video(&self) -> Option<&ProbeStream> {
+        self.streams.iter().find(|s| s.codec_type == "video")
+    }
+
+    /// 第一个音频流
+    pub fn audio(&self) -> Option<&ProbeStream> {
+        self.streams.iter().find(|s| s.codec_type == "audio")
+    }
+
+    /// 根据探测结果决定剪辑为MP4时的编码参数
+    ///
+    /// 视频编码MP4可容纳时直接复制，否则转码为H.264；
+    /// 音频为AAC/MP3时直接复制，其他编码（如G.711 pcm_alaw）转码为AAC，没有音频时去掉音轨。
+    pub fn mp4_codec_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        match self.video().and_then(|v| v.codec.as_deref()) {
+            Some(codec) if MP4_COPYABLE_VIDEO.contains(&codec) => {
+                args.extend(["-c:v".to_string(), "copy".to_string()]);
+            }
+            _ => {
+                args.extend([
+                    "-c:v".to_string(), "libx264".to_string(),
+                    "-preset".to_string(), "fast".to_string(),
+                    "-crf".to_string(), "23".to_string(),
+                ]);
+            }
+        }
+
+        match self.audio().and_then(|a| a.codec.as_deref()) {
+            None => args.push("-an".to_string()),
+            Some(codec) if MP4_COPYABLE_AUDIO.contains(&codec) => {
+                args.extend(["-c:a".to_string(), "copy".to_string()]);
+            }
+            Some(_) => {
+                args.extend([
+                    "-c:a".to_string(), "aac".to_string(),
+                    "-b:a".to_string(), "128k".to_string(),
+                ]);
+            }
+        }
+
+        args
+    }
+}
+
+impl ProbeStream {
+    fn from_json(stream: &Value) -> Self {
+        // 实时流的 avg_frame_rate 经常是 0/0，此时退回 r_frame_rate
+        let frame_rate = parse_rate(stream, "avg_frame_rate")
+            .or_else(|| parse_rate(stream, "r_frame_rate"));
+
+        Self {
+            index: stream["index"].as_u64().unwrap_or(0) as u32,
+            codec_type: str_field(stream, "codec_type").unwrap_or_else(|| "unknown".to_string()),
+            codec: str_field(stream, "codec_name"),
+            profile: str_field(stream, "profile"),
+            width: stream["width"].as_u64().map(|v| v as u32),
+            height: stream["height"].as_u64().map(|v| v as u32),
+            frame_rate,
+            pixel_format: str_field(stream, "pix_fmt"),
+            bit_rate: num_field(stream, "bit_rate"),
+            channels: stream["channels"].as_u64().map(|v| v as u32),
+            channel_layout: str_field(stream, "channel_layout"),
+            sample_rate: num_field(stream, "sample_rate"),
+        }
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value[key].as_str().map(|s| s.to_string())
+}
+
+/// ffprobe把大部分数值输出为字符串
+fn num_field<T: std::str::FromStr>(value: &Value, key: &str) -> Option<T> {
+    match &value[key] {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// 解析 `25/1` 格式的帧率
+fn parse_rate(value: &Value, key: &str) -> Option<f64> {
+    let rate = value[key].as_str()?;
+    let (num, den) = rate.split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+/// 去掉ffprobe不支持的ffmpeg专有输入选项
+pub fn probe_input_args(input_args: Vec<String>) -> Vec<String> {
+    const FFMPEG_ONLY: &[&str] = &["-thread_queue_size"];
+
+    let mut args = Vec::new();
+    let mut iter = input_args.into_iter();
+    while let Some(arg) = iter.next() {
+        if FFMPEG_ONLY.contains(&arg.as_str()) {
+            iter.next();
+            continue;
+        }
+        args.push(arg);
+    }
+    args
+}
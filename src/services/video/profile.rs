@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use super::{RtspTransport, StreamHandler, StreamProtocol};
+
+/// 编码参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodeSettings {
+    /// 视频编码器，`copy` 表示直接复制
+    pub video_codec: String,
+    pub preset: Option<String>,
+    pub crf: Option<u32>,
+    /// 音频编码器，None表示沿用ffmpeg默认行为
+    pub audio_codec: Option<String>,
+    pub audio_bitrate: Option<String>,
+    /// 追加在编码参数之后的额外ffmpeg参数
+    pub extra_args: Vec<String>,
+}
+
+impl Default for EncodeSettings {
+    fn default() -> Self {
+        Self {
+            video_codec: "copy".to_string(),
+            preset: None,
+            crf: None,
+            audio_codec: Some("aac".to_string()),
+            audio_bitrate: Some("128k".to_string()),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+impl EncodeSettings {
+    /// 转换为ffmpeg参数
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec!["-c:v".to_string(), self.video_codec.clone()];
+
+        if let Some(preset) = &self.preset {
+            args.extend(["-preset".to_string(), preset.clone()]);
+        }
+        if let Some(crf) = self.crf {
+            args.extend(["-crf".to_string(), crf.to_string()]);
+        }
+        if let Some(audio_codec) = &self.audio_codec {
+            args.extend(["-c:a".to_string(), audio_codec.clone()]);
+        }
+        if let Some(audio_bitrate) = &self.audio_bitrate {
+            args.extend(["-b:a".to_string(), audio_bitrate.clone()]);
+        }
+        args.extend(self.extra_args.iter().cloned());
+
+        args
+    }
+}
+
+/// 摄像头配置
+///
+/// 按厂商/型号描述拉流和编码参数，通过URL模式自动匹配或在请求中按名称指定。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraProfile {
+    pub name: String,
+    pub vendor: Option<String>,
+    /// URL匹配模式，`*` 匹配任意字符，例如 `*/cam/realmonitor*`
+    pub url_patterns: Vec<String>,
+    /// RTSP传输方式，仅对 rtsp:// 地址生效
+    pub transport: RtspTransport,
+    /// 探测大小（字节）
+    pub probe_size: Option<u64>,
+    /// 分析时长（微秒）
+    pub analyze_duration: Option<u64>,
+    /// 网络读写超时（秒）
+    pub io_timeout_secs: u64,
+    /// 截图的ffmpeg超时（秒）
+    pub snapshot_timeout_secs: u64,
+    /// 剪辑的ffmpeg超时余量（秒），实际超时为剪辑时长加上该余量
+    pub clip_timeout_margin_secs: u64,
+    /// 禁用缓冲并启用低延迟模式
+    pub low_latency: bool,
+    /// 剪辑时重新生成PTS，用于时间戳不规整的设备
+    pub gen_pts: bool,
+    /// 截图时是否按时间戳seek，实时画面可以关闭直接取第一帧
+    pub snapshot_seek: bool,
+    /// 剪辑编码参数
+    pub encode: EncodeSettings,
+}
+
+impl Default for CameraProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            vendor: None,
+            url_patterns: Vec::new(),
+            transport: RtspTransport::TCP,
+            probe_size: None,
+            analyze_duration: None,
+            io_timeout_secs: 10,
+            snapshot_timeout_secs: 30,
+            clip_timeout_margin_secs: 30,
+            low_latency: true,
+            gen_pts: false,
+            snapshot_seek: true,
+            encode: EncodeSettings::default(),
+        }
+    }
+}
+
+impl CameraProfile {
+    /// 大华 realmonitor 摄像头
+    ///
+    /// 这类设备探测时间长且时间戳不规整，截图不做seek，剪辑时重新编码并生成PTS。
+    pub fn dahua() -> Self {
+        Self {
+            name: "dahua".to_string(),
+            vendor: Some("Dahua".to_string()),
+            url_patterns: vec!["*realmonitor*".to_string()],
+            gen_pts: true,
+            snapshot_seek: false,
+            encode: EncodeSettings {
+                video_codec: "libx264".to_string(),
+                preset: Some("ultrafast".to_string()),
+                crf: Some(28),
+                extra_args: vec!["-avoid_negative_ts".to_string(), "make_zero".to_string()],
+                ..EncodeSettings::default()
+            },
+            ..Self::default()
+        }
+    }
+
+    /// URL是否匹配该配置
+    pub fn matches(&self, url: &str) -> bool {
+        self.url_patterns.iter().any(|pattern| wildcard_match(pattern, url))
+    }
+}
+
+/// 简单通配符匹配，只支持 `*`
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let first = parts[0];
+    let last = parts[parts.len() - 1];
+    // 先去掉首尾部分，中间部分只在剩余文本中查找，避免按字节切片落在多字节字符中间
+    let Some(mut rest) = text.strip_prefix(first).and_then(|rest| rest.strip_suffix(last)) else {
+        return false;
+    };
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+/// 配置文件格式
+#[derive(Debug, Default, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: Vec<CameraProfile>,
+}
+
+/// 摄像头配置注册表
+///
+/// 内置大华配置，配置文件中的同名配置会覆盖内置配置。
+/// URL匹配按配置文件中的顺序进行，内置配置最后匹配。
+#[derive(Debug, Clone)]
+pub struct ProfileRegistry {
+    profiles: Vec<CameraProfile>,
+    by_name: HashMap<String, usize>,
+}
+
+impl ProfileRegistry {
+    /// 只包含内置配置
+    pub fn builtin() -> Self {
+        Self::from_profiles(vec![CameraProfile::dahua()])
+    }
+
+    fn from_profiles(profiles: Vec<CameraProfile>) -> Self {
+        let by_name = profiles
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.name.clone(), i))
+            .collect();
+
+        Self { profiles, by_name }
+    }
+
+    /// 从配置文件加载，文件不存在时只使用内置配置
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !Path::new(path).exists() {
+            tracing::info!("Camera profile file {} not found, using built-in profiles", path);
+            return Ok(Self::builtin());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("读取摄像头配置文件失败 {}: {}", path, e))?;
+        let file: ProfilesFile = serde_json::from_str(&content)
+            .map_err(|e| format!("解析摄像头配置文件失败 {}: {}", path, e))?;
+
+        let mut profiles = Vec::new();
+        for profile in file.profiles {
+            if profile.name.is_empty() {
+                return Err(format!("摄像头配置缺少name: {}", path));
+            }
+            if profiles.iter().any(|p: &CameraProfile| p.name == profile.name) {
+                return Err(format!("摄像头配置重复: {}", profile.name));
+            }
+            profiles.push(profile);
+        }
+
+        for builtin in Self::builtin().profiles {
+            if !profiles.iter().any(|p| p.name == builtin.name) {
+                profiles.push(builtin);
+            }
+        }
+
+        tracing::info!("Loaded {} camera profiles from {}", profiles.len(), path);
+        Ok(Self::from_profiles(profiles))
+    }
+
+    /// 按名称查找配置
+    pub fn get(&self, name: &str) -> Option<&CameraProfile> {
+        self.by_name.get(name).map(|&i| &self.profiles[i])
+    }
+
+    /// 查找第一个匹配URL的配置
+    pub fn match_url(&self, url: &str) -> Option<&CameraProfile> {
+        self.profiles.iter().find(|p| p.matches(url))
+    }
+
+    /// 全部配置
+    pub fn list(&self) -> &[CameraProfile] {
+        &self.profiles
+    }
+}
+
+impl Default for ProfileRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// 按摄像头配置生成参数的流处理器
+pub struct ProfileHandler {
+    profile: CameraProfile,
+    protocol: StreamProtocol,
+}
+
+impl ProfileHandler {
+    pub fn new(profile: CameraProfile, url: &str) -> Self {
+        let protocol = if url.starts_with("rtsp://") {
+            StreamProtocol::RTSP
+        } else if url.starts_with("rtmp://") {
+            StreamProtocol::RTMP
+        } else if url.starts_with("http://") || url.starts_with("https://") {
+            StreamProtocol::HTTP
+        } else {
+            StreamProtocol::Unknown
+        };
+
+        Self { profile, protocol }
+    }
+
+    /// 输入选项，`fflags` 可以追加额外标志
+    fn input_args_with_fflags(&self, extra_fflags: Option<&str>) -> Vec<String> {
+        let p = &self.profile;
+        let mut args = Vec::new();
+
+        if self.protocol == StreamProtocol::RTSP {
+            args.extend(["-rtsp_transport".to_string(), p.transport.as_str().to_string()]);
+        }
+        args.extend([
+            "-timeout".to_string(),
+            Duration::from_secs(p.io_timeout_secs).as_micros().to_string(),
+        ]);
+
+        let mut fflags = Vec::new();
+        if p.low_latency {
+            fflags.push("nobuffer");
+        }
+        if let Some(extra) = extra_fflags {
+            fflags.push(extra);
+        }
+        if !fflags.is_empty() {
+            args.extend(["-fflags".to_string(), fflags.join("+")]);
+        }
+        if p.low_latency {
+            args.extend(["-flags".to_string(), "low_delay".to_string()]);
+        }
+
+        args.extend(["-strict".to_string(), "experimental".to_string()]);
+        if let Some(analyze_duration) = p.analyze_duration {
+            args.extend(["-analyzeduration".to_string(), analyze_duration.to_string()]);
+        }
+        if let Some(probe_size) = p.probe_size {
+            args.extend(["-probesize".to_string(), probe_size.to_string()]);
+        }
+        args.extend(["-thread_queue_size".to_string(), "512".to_string()]);
+
+        args
+    }
+}
+
+impl StreamHandler for ProfileHandler {
+    fn protocol(&self) -> StreamProtocol {
+        self.protocol
+    }
+
+    fn validate_url(&self, url: &str) -> bool {
+        self.profile.matches(url)
+    }
+
+    fn input_args(&self, _url: &str) -> Vec<String> {
+        self.input_args_with_fflags(None)
+    }
+
+    fn clip_codec_args(&self) -> Vec<String> {
+        self.profile.encode.to_args()
+    }
+
+    /// 摄像头配置明确给出了编码参数，不需要探测
+    fn probe_before_clip(&self) -> bool {
+        false
+    }
+
+    fn snapshot_timeout(&self) -> Duration {
+        Duration::from_secs(self.profile.snapshot_timeout_secs)
+    }
+
+    fn clip_timeout_margin(&self) -> Duration {
+        Duration::from_secs(self.profile.clip_timeout_margin_secs)
+    }
+
+    fn build_snapshot_args(
+        &self,
+        url: &str,
+        timestamp: f64,
+        output_args: Vec<String>,
+        output: &str,
+    ) -> Vec<String> {
+        let mut args = self.input_args(url);
+        args.extend(["-i".to_string(), url.to_string()]);
+        if self.profile.snapshot_seek {
+            args.extend(["-ss".to_string(), timestamp.to_string()]);
+        }
+        args.extend(["-vframes".to_string(), "1".to_string()]);
+        args.extend(output_args);
+        args.extend(["-y".to_string(), output.to_string()]);
+        args
+    }
+
+    fn build_clip_args(
+        &self,
+        url: &str,
+        start: f64,
+        duration: f64,
+        codec_args: Vec<String>,
+        output: &str,
+    ) -> Vec<String> {
+        let fflags = self.profile.gen_pts.then_some("genpts");
+        let mut args = self.input_args_with_fflags(fflags);
+        args.extend([
+            "-ss".to_string(), start.to_string(),
+            "-i".to_string(), url.to_string(),
+            "-t".to_string(), duration.to_string(),
+        ]);
+        args.extend(codec_args);
+        args.extend(["-y".to_string(), output.to_string()]);
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wildcard_match;
+
+    #[test]
+    fn wildcard_non_ascii_text() {
+        let pattern = "rtsp://*/Streaming/Channels/*01";
+        assert!(!wildcard_match(pattern, "rtsp://h/Streaming/Channels/中"));
+        assert!(wildcard_match(pattern, "rtsp://摄像头/Streaming/Channels/101"));
+        assert!(wildcard_match("*中*", "rtsp://h/中文"));
+    }
+
+    #[test]
+    fn wildcard_overlapping_prefix_suffix() {
+        assert!(!wildcard_match("a*a", "a"));
+        assert!(wildcard_match("a*a", "aa"));
+        assert!(wildcard_match("a*a", "aba"));
+        assert!(!wildcard_match("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn wildcard_empty_pattern() {
+        assert!(wildcard_match("", ""));
+        assert!(!wildcard_match("", "rtsp://h"));
+    }
+
+    #[test]
+    fn wildcard_star_only() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*", "rtsp://h/中"));
+        assert!(wildcard_match("**", "abc"));
+        assert!(wildcard_match("*/*", "rtsp://h"));
+        assert!(!wildcard_match("*/*", "abc"));
+    }
+}
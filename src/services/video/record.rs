@@ -0,0 +1,54 @@
+use std::path::Path;
+use std::time::Duration;
+
+use super::{FfmpegCommand, VideoSnapshotService};
+
+/// 录像片段文件名格式（UTC），片段开始时间由文件名确定
+const SEGMENT_NAME_FORMAT: &str = "%Y%m%dT%H%M%SZ.ts";
+
+impl VideoSnapshotService {
+    /// 持续录制视频流，按时间切分为MPEG-TS片段写入 `dir`，直到ffmpeg退出或返回的future被丢弃
+    ///
+    /// 原样复制音视频，片段在整 `segment_secs` 的时刻之后的第一个关键帧处切分，
+    /// 文件名是片段开始的UTC时间（见 `SEGMENT_NAME_FORMAT`）
+    pub async fn record_segments(
+        &self,
+        url: &str,
+        profile: Option<&str>,
+        dir: &Path,
+        segment_secs: u64,
+    ) -> Result<(), String> {
+        let handler = self.resolve_handler(url, profile)?;
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create recording dir: {}", e))?;
+
+        let mut args = handler.input_args(url);
+        args.extend([
+            "-i".to_string(), url.to_string(),
+            "-loglevel".to_string(), "error".to_string(),
+            "-map".to_string(), "0:v:0".to_string(),
+            "-map".to_string(), "0:a:0?".to_string(),
+            "-c".to_string(), "copy".to_string(),
+            "-f".to_string(), "segment".to_string(),
+            "-segment_time".to_string(), segment_secs.to_string(),
+            "-segment_atclocktime".to_string(), "1".to_string(),
+            "-segment_format".to_string(), "mpegts".to_string(),
+            "-reset_timestamps".to_string(), "1".to_string(),
+            "-strftime".to_string(), "1".to_string(),
+            dir.join(SEGMENT_NAME_FORMAT).to_string_lossy().into_owned(),
+        ]);
+
+        // 文件名按UTC时间生成，与服务器时区无关；录像不设超时，只输出错误日志以免stderr无限增长
+        let output = FfmpegCommand::new(args)
+            .env("TZ", "UTC")
+            .timeout(Duration::MAX)
+            .run()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("FFmpeg recording failed: {}", stderr));
+        }
+        Ok(())
+    }
+}
@@ -1,159 +1,201 @@
-use tempfile::NamedTempFile;
-use std::process::Command;
 use uuid::Uuid;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::timeout;
+
+use crate::services::source::redact_url;
+use super::{
+    apply_masks, probe_input_args, CameraProfile, ClipOutput, CropRegion, EncodingProfile, EncodingRegistry, FfmpegCommand, FilterGraph, OverlaySpec,
+    PartialOutputGuard, PrivacyMask, ProbeResult, ProfileHandler, ProfileRegistry, ProgressCallback, ResolvedOutput, SnapshotOptions, StreamHandler,
+    StreamHandlerRegistry,
+};
+
+/// 多帧截图中的一帧
+pub struct CapturedFrame {
+    /// 截图时间戳（秒）
+    pub timestamp: f64,
+    pub data: Vec<u8>,
+}
 
 #[derive(Clone)]
-pub struct VideoSnapshotService;
-
-#[derive(Debug, Clone)]
-pub enum StreamProtocol {
-    RTSP,
-    RTMP,
-    HLS,
-    HTTP,
-    File,
-    Unknown,
+pub struct VideoSnapshotService {
+    handlers: StreamHandlerRegistry,
+    profiles: Arc<ProfileRegistry>,
+    encodings: Arc<EncodingRegistry>,
+    /// 水印图片目录，叠加请求只能引用该目录下的文件
+    watermark_dir: PathBuf,
+    /// 剪辑、音频、预览等输出文件的目录
+    clips_dir: PathBuf,
 }
 
 impl VideoSnapshotService {
-    pub fn new() -> Self {
-        VideoSnapshotService
+    /// 使用指定的流处理器注册表、摄像头配置和编码配置创建服务
+    pub fn new(handlers: StreamHandlerRegistry, profiles: ProfileRegistry, encodings: EncodingRegistry) -> Self {
+        Self {
+            handlers,
+            profiles: Arc::new(profiles),
+            encodings: Arc::new(encodings),
+            watermark_dir: PathBuf::from("watermarks"),
+            clips_dir: PathBuf::from("clips"),
+        }
     }
 
-    /// 检查ffmpeg版本是否支持reconnect选项
-    fn check_ffmpeg_supports_reconnect() -> bool {
-        let output = Command::new("ffmpeg")
-            .args(["-h", "full"])
-            .output();
-        
-        match output {
-            Ok(out) => {
-                let stdout = String::from_utf8_lossy(&out.stdout);
-                stdout.contains("-reconnect")
-            }
-            Err(_) => false
+    /// 设置水印图片目录
+    pub fn with_watermark_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.watermark_dir = dir.into();
+        self
+    }
+
+    /// 设置输出文件目录
+    pub fn with_clips_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.clips_dir = dir.into();
+        self
+    }
+
+    /// 输出目录下文件的路径
+    pub fn clip_path(&self, name: &str) -> String {
+        self.clips_dir.join(name).to_string_lossy().into_owned()
+    }
+
+    /// 全部摄像头配置
+    pub fn profiles(&self) -> &[CameraProfile] {
+        self.profiles.list()
+    }
+
+    /// 全部命名编码配置
+    pub fn encodings(&self) -> &[EncodingProfile] {
+        self.encodings.list()
+    }
+
+    /// 检查剪辑输出设置：编码配置是否存在、容器与编码是否兼容、遮挡区域和水印文件是否有效
+    pub fn validate_output(&self, output: &ClipOutput) -> Result<(), String> {
+        self.encodings.resolve(output)?;
+        output.validate()?;
+        self.validate_overlay(output.overlay.as_ref())
+    }
+
+    /// 解析剪辑输出的编码方案
+    pub(super) fn resolve_output(&self, output: &ClipOutput) -> Result<ResolvedOutput, String> {
+        self.encodings.resolve(output)
+    }
+
+    /// 检查叠加参数和水印文件
+    pub fn validate_overlay(&self, overlay: Option<&OverlaySpec>) -> Result<(), String> {
+        let Some(overlay) = overlay else {
+            return Ok(());
+        };
+        overlay.validate()?;
+        if let Some(watermark) = &overlay.watermark
+            && !self.watermark_dir.join(&watermark.file).is_file()
+        {
+            return Err(format!("水印文件不存在: {}", watermark.file));
         }
+        Ok(())
     }
 
-    /// 检测流协议类型
-    fn detect_protocol(url: &str) -> StreamProtocol {
-        if url.starts_with("rtsp://") {
-            StreamProtocol::RTSP
-        } else if url.starts_with("rtmp://") {
-            StreamProtocol::RTMP
-        } else if url.contains(".m3u8") || url.starts_with("hls://") {
-            StreamProtocol::HLS
-        } else if url.starts_with("http://") || url.starts_with("https://") {
-            StreamProtocol::HTTP
-        } else if url.starts_with("file://") || (!url.contains("://") && std::path::Path::new(url).exists()) {
-            StreamProtocol::File
-        } else {
-            StreamProtocol::Unknown
+    /// 组合 `-vf` 滤镜图：隐私遮挡、裁剪、缩放，最后叠加文字和水印
+    ///
+    /// 没有任何滤镜时返回None，蒙版和叠加文字写入 `work_dir`
+    fn video_filter(
+        &self,
+        masks: &[PrivacyMask],
+        crop: Option<&CropRegion>,
+        scale: Option<String>,
+        overlay: Option<&OverlaySpec>,
+        work_dir: &Path,
+    ) -> Result<Option<String>, String> {
+        let mut graph = FilterGraph::new("in");
+        apply_masks(masks, &mut graph, work_dir)?;
+        if let Some(crop) = crop {
+            graph.push(crop.filter());
+        }
+        if let Some(scale) = scale {
+            graph.push(scale);
+        }
+        if let Some(overlay) = overlay {
+            overlay.apply(&mut graph, work_dir, &self.watermark_dir)?;
         }
+        Ok(graph.into_vf())
     }
 
-    /// 根据协议类型生成特定的ffmpeg参数
-    fn get_protocol_args(protocol: &StreamProtocol, url: &str) -> Vec<String> {
-        match protocol {
-            StreamProtocol::RTSP => {
-                // Docker环境优化的RTSP参数
-                if url.contains("realmonitor") {
-                    vec![
-                        "-rtsp_transport".to_string(), "tcp".to_string(),
-                        "-fflags".to_string(), "nobuffer".to_string(),
-                        "-flags".to_string(), "low_delay".to_string(),
-                        "-strict".to_string(), "experimental".to_string(),
-                        "-analyzeduration".to_string(), "1000000".to_string(), // 1秒分析时间
-                        "-probesize".to_string(), "1000000".to_string(), // 1MB探测大小
-                        "-timeout".to_string(), "10000000".to_string(), // 10秒超时
-                        "-thread_queue_size".to_string(), "512".to_string(),
-                    ]
-                } else {
-                    vec![
-                        "-rtsp_transport".to_string(), "tcp".to_string(),
-                        "-timeout".to_string(), "10000000".to_string(),
-                        "-fflags".to_string(), "nobuffer".to_string(),
-                        "-flags".to_string(), "low_delay".to_string(),
-                        "-strict".to_string(), "experimental".to_string(),
-                        "-analyzeduration".to_string(), "5000000".to_string(),
-                        "-probesize".to_string(), "5000000".to_string(),
-                        "-max_delay".to_string(), "500000".to_string(),
-                        "-thread_queue_size".to_string(), "512".to_string(),
-                    ]
-                }
-            },
-            StreamProtocol::RTMP => vec![
-                "-timeout".to_string(), "10000000".to_string(),
-                "-analyzeduration".to_string(), "2000000".to_string(),
-                "-probesize".to_string(), "2000000".to_string(),
-            ],
-            StreamProtocol::HLS => vec![
-                "-timeout".to_string(), "10000000".to_string(),
-                "-user_agent".to_string(), "Mozilla/5.0 (compatible; VideoServer/1.0)".to_string(),
-                "-analyzeduration".to_string(), "3000000".to_string(),
-                "-probesize".to_string(), "3000000".to_string(),
-            ],
-            StreamProtocol::HTTP => vec![
-                "-user_agent".to_string(), "Mozilla/5.0 (compatible; VideoServer/1.0)".to_string(),
-                "-timeout".to_string(), "10000000".to_string(),
-            ],
-            StreamProtocol::File => vec![],
-            StreamProtocol::Unknown => vec![
-                "-timeout".to_string(), "10000000".to_string(),
-                "-analyzeduration".to_string(), "3000000".to_string(),
-                "-probesize".to_string(), "3000000".to_string(),
-            ],
+    /// 检查请求指定的摄像头配置是否存在
+    pub fn validate_profile(&self, profile: Option<&str>) -> Result<(), String> {
+        match profile {
+            Some(name) if self.profiles.get(name).is_none() => {
+                Err(format!("未知的摄像头配置: {}", name))
+            }
+            _ => Ok(()),
         }
     }
 
-    /// 异步执行ffmpeg命令，带超时控制
-    async fn execute_ffmpeg_with_timeout(args: Vec<String>) -> Result<std::process::Output, String> {
-        let mut cmd = Command::new("ffmpeg");
-        cmd.args(&args);
-        
-        tracing::info!("Executing ffmpeg command: {:?}", cmd);
-        
-        // 使用tokio的spawn_blocking在后台线程执行阻塞操作
-        let cmd_future = tokio::task::spawn_blocking(move || {
-            cmd.output()
-        });
-        
-        // 设置30秒超时
-        match timeout(Duration::from_secs(30), cmd_future).await {
-            Ok(task_result) => {
-                match task_result {
-                    Ok(cmd_result) => {
-                        match cmd_result {
-                            Ok(output) => {
-                                tracing::info!("FFmpeg command completed");
-                                Ok(output)
-                            },
-                            Err(e) => {
-                                tracing::error!("FFmpeg execution error: {}", e);
-                                Err(format!("Failed to execute ffmpeg: {}", e))
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        tracing::error!("Task join error: {}", e);
-                        Err(format!("Task execution failed: {}", e))
-                    }
-                }
-            },
-            Err(_) => {
-                tracing::error!("FFmpeg command timeout after 30 seconds");
-                Err("FFmpeg command timeout after 30 seconds".to_string())
+    /// 选择流处理器
+    ///
+    /// 优先使用请求指定的摄像头配置，其次是URL匹配的摄像头配置，最后按协议选择
+    pub(super) fn resolve_handler(&self, url: &str, profile: Option<&str>) -> Result<Arc<dyn StreamHandler>, String> {
+        let profile = match profile {
+            Some(name) => Some(
+                self.profiles.get(name)
+                    .ok_or_else(|| format!("未知的摄像头配置: {}", name))?
+            ),
+            None => self.profiles.match_url(url),
+        };
+
+        let handler: Arc<dyn StreamHandler> = match profile {
+            Some(profile) => {
+                tracing::info!("Using camera profile: {}", profile.name);
+                Arc::new(ProfileHandler::new(profile.clone(), url))
             }
+            None => self.handlers.resolve(url),
+        };
+        tracing::info!("Detected protocol: {:?}", handler.protocol());
+
+        Ok(handler)
+    }
+
+    /// 用ffprobe探测视频流的容器和各路流信息
+    pub async fn probe(&self, url: &str, profile: Option<&str>) -> Result<ProbeResult, String> {
+        tracing::info!("Starting probe for URL: {}", redact_url(url));
+
+        let handler = self.resolve_handler(url, profile)?;
+        Self::run_probe(handler.as_ref(), url).await
+    }
+
+    /// 使用处理器的协议参数运行ffprobe
+    async fn run_probe(handler: &dyn StreamHandler, url: &str) -> Result<ProbeResult, String> {
+        let mut args: Vec<String> = ["-v", "error", "-print_format", "json", "-show_format", "-show_streams"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        args.extend(probe_input_args(handler.input_args(url)));
+        args.extend(["-i".to_string(), url.to_string()]);
+
+        let output = FfmpegCommand::ffprobe(args)
+            .timeout(handler.snapshot_timeout())
+            .run()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::error!("FFprobe failed - stderr: {}", stderr);
+            return Err(format!("FFprobe failed: {}", stderr));
         }
+
+        ProbeResult::from_ffprobe_json(&output.stdout)
     }
 
-    /// 截取视频流指定时间的图片，返回 PNG 二进制
-    pub async fn capture_frame(&self, url: &str, timestamp: f64) -> Result<Vec<u8>, String> {
-        tracing::info!("Starting capture_frame for URL: {}, timestamp: {}", url, timestamp);
+    /// 截取视频流指定时间的图片，按 `options` 指定的格式和尺寸返回图片二进制
+    pub async fn capture_frame(
+        &self,
+        url: &str,
+        timestamp: f64,
+        profile: Option<&str>,
+        options: &SnapshotOptions,
+    ) -> Result<Vec<u8>, String> {
+        tracing::info!("Starting capture_frame for URL: {}, timestamp: {}", redact_url(url), timestamp);
         
-        let temp_file = NamedTempFile::new()
+        let temp_file = tempfile::Builder::new()
+            .suffix(&format!(".{}", options.format.extension()))
+            .tempfile()
             .map_err(|e| {
                 tracing::error!("Failed to create temp file: {}", e);
                 format!("Failed to create temp file: {}", e)
@@ -163,50 +205,17 @@ impl VideoSnapshotService {
         
         tracing::info!("Created temporary file: {}", output_path);
         
-        let protocol = Self::detect_protocol(url);
-        tracing::info!("Detected protocol: {:?}", protocol);
-        
-        // 为特定摄像头使用Docker优化的命令
-        let args = if url.contains("realmonitor") {
-            tracing::info!("Using Docker-optimized command for realmonitor camera");
-            vec![
-                "-rtsp_transport".to_string(), "tcp".to_string(),
-                "-timeout".to_string(), "10000000".to_string(), // 10秒超时
-                "-fflags".to_string(), "nobuffer".to_string(), // 禁用缓冲
-                "-flags".to_string(), "low_delay".to_string(), // 低延迟模式
-                "-strict".to_string(), "experimental".to_string(), // 实验性特性
-                "-thread_queue_size".to_string(), "512".to_string(), // 线程队列大小
-                "-i".to_string(), url.to_string(),
-                "-vframes".to_string(), "1".to_string(),
-                "-q:v".to_string(), "2".to_string(), // 高质量截图
-                "-f".to_string(), "image2".to_string(),
-                "-y".to_string(),
-                output_path.to_string(),
-            ]
-        } else {
-            let protocol_args = Self::get_protocol_args(&protocol, url);
-            let timestamp_str = timestamp.to_string();
-            
-            let mut args = Vec::new();
-            
-            // 添加协议特定参数
-            args.extend(protocol_args);
-            
-            // 添加基本参数
-            args.extend(vec![
-                "-i".to_string(), url.to_string(),
-                "-ss".to_string(), timestamp_str,
-                "-vframes".to_string(), "1".to_string(),
-                "-q:v".to_string(), "2".to_string(), // 高质量截图
-                "-f".to_string(), "image2".to_string(),
-                "-y".to_string(),
-                output_path.to_string(),
-            ]);
-            
-            args
-        };
+        let work_dir = tempfile::tempdir()
+            .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+        let filter = self.video_filter(&options.masks, options.crop.as_ref(), options.scale_filter(), options.overlay.as_ref(), work_dir.path())?;
+
+        let handler = self.resolve_handler(url, profile)?;
+        let args = handler.build_snapshot_args(url, timestamp, options.output_args(filter), output_path);
 
-        let output = Self::execute_ffmpeg_with_timeout(args).await?;
+        let output = FfmpegCommand::new(args)
+            .timeout(handler.snapshot_timeout())
+            .run()
+            .await?;
             
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -240,102 +249,153 @@ impl VideoSnapshotService {
         Ok(image_data)
     }
 
+    /// 一次连接截取多帧图片，按时间戳顺序返回
+    ///
+    /// 流提前结束时缺少的帧会被跳过，一帧都没有时返回错误
+    pub async fn capture_frames(
+        &self,
+        url: &str,
+        timestamps: &[f64],
+        profile: Option<&str>,
+        options: &SnapshotOptions,
+    ) -> Result<Vec<CapturedFrame>, String> {
+        tracing::info!("Starting capture_frames for URL: {}, {} frames", redact_url(url), timestamps.len());
+
+        let temp_dir = tempfile::tempdir()
+            .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+        let frames: Vec<(f64, String)> = timestamps
+            .iter()
+            .enumerate()
+            .map(|(i, &timestamp)| {
+                let path = temp_dir.path().join(format!("frame_{:03}.{}", i, options.format.extension()));
+                (timestamp, path.to_string_lossy().into_owned())
+            })
+            .collect();
+
+        let filter = self.video_filter(&options.masks, options.crop.as_ref(), options.scale_filter(), options.overlay.as_ref(), temp_dir.path())?;
+
+        let handler = self.resolve_handler(url, profile)?;
+        let args = handler.build_batch_snapshot_args(url, &frames, &options.output_args(filter));
+
+        // 实时流需要读到最后一个时间戳，超时随之增加
+        let last = timestamps.iter().cloned().fold(0.0, f64::max);
+        let output = FfmpegCommand::new(args)
+            .timeout(handler.snapshot_timeout() + Duration::from_secs_f64(last))
+            .run()
+            .await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::error!("FFmpeg batch snapshot failed - stderr: {}", stderr);
+            return Err(format!("FFmpeg failed: {}", stderr));
+        }
+
+        let mut captured = Vec::new();
+        for (timestamp, path) in frames {
+            match std::fs::read(&path) {
+                Ok(data) if !data.is_empty() => captured.push(CapturedFrame { timestamp, data }),
+                _ => tracing::warn!("No frame captured at timestamp {}", timestamp),
+            }
+        }
+
+        if captured.is_empty() {
+            return Err("No frames were captured".to_string());
+        }
+        tracing::info!("Captured {} of {} frames", captured.len(), timestamps.len());
+
+        Ok(captured)
+    }
+
     /// 截取视频流一段，保存为本地文件，返回文件名
-    pub async fn clip_video(&self, url: &str, start: f64, duration: f64) -> Result<String, String> {
-        tracing::info!("Starting clip_video for URL: {}, start: {}, duration: {}", url, start, duration);
+    ///
+    /// `output` 指定容器、编码、遮挡、裁剪缩放和叠加内容，未指定编码时沿用流处理器或摄像头配置的默认编码，
+    /// 有滤镜时默认转码为H.264。`on_progress` 会在ffmpeg每次输出进度时被调用
+    pub async fn clip_video(
+        &self,
+        url: &str,
+        start: f64,
+        duration: f64,
+        profile: Option<&str>,
+        output: &ClipOutput,
+        on_progress: Option<ProgressCallback>,
+    ) -> Result<String, String> {
+        let handler = self.resolve_handler(url, profile)?;
+        self.clip_with_handler(handler, url, start, duration, output, on_progress).await
+    }
+
+    /// 用指定的流处理器剪辑，返回文件名
+    pub(super) async fn clip_with_handler(
+        &self,
+        handler: Arc<dyn StreamHandler>,
+        url: &str,
+        start: f64,
+        duration: f64,
+        output: &ClipOutput,
+        on_progress: Option<ProgressCallback>,
+    ) -> Result<String, String> {
+        tracing::info!("Starting clip_video for URL: {}, start: {}, duration: {}", redact_url(url), start, duration);
         
-        let filename = format!("{}.mp4", Uuid::new_v4());
-        let output_path = format!("clips/{}", filename);
+        let resolved = self.encodings.resolve(output)?;
+        let id = Uuid::new_v4();
+        // 打包输出放在独立子目录，返回入口文件的相对路径
+        let (filename, package_dir) = match resolved.package {
+            Some(package) => (
+                format!("{}/{}", id, package.manifest_name()),
+                Some(self.clip_path(&id.to_string())),
+            ),
+            None => (format!("{}.{}", id, resolved.container.extension()), None),
+        };
+        let output_path = self.clip_path(&filename);
         
         tracing::info!("Output file will be: {}", output_path);
-        
-        let protocol = Self::detect_protocol(url);
-        tracing::info!("Detected protocol: {:?}", protocol);
-        
-        // 为特定摄像头使用Docker优化的命令
-        let args = if url.contains("realmonitor") {
-            tracing::info!("Using Docker-optimized command for realmonitor camera");
-            vec![
-                "-rtsp_transport".to_string(), "tcp".to_string(),
-                "-timeout".to_string(), "10000000".to_string(), // 10秒超时
-                "-fflags".to_string(), "nobuffer+genpts".to_string(), // 禁用缓冲+生成PTS
-                "-flags".to_string(), "low_delay".to_string(), // 低延迟模式
-                "-strict".to_string(), "experimental".to_string(), // 实验性特性
-                "-thread_queue_size".to_string(), "512".to_string(), // 线程队列大小
-                "-ss".to_string(), start.to_string(),
-                "-i".to_string(), url.to_string(),
-                "-t".to_string(), duration.to_string(),
-                "-c:v".to_string(), "libx264".to_string(), // 重新编码确保兼容性
-                "-preset".to_string(), "ultrafast".to_string(), // 最快编码
-                "-crf".to_string(), "28".to_string(), // 压缩率
-                "-c:a".to_string(), "aac".to_string(),  // 音频重新编码为AAC
-                "-b:a".to_string(), "128k".to_string(), // 音频比特率
-                "-avoid_negative_ts".to_string(), "make_zero".to_string(), // 避免负时间戳
-                "-y".to_string(),
-                output_path.clone(),
-            ]
-        } else {
-            let protocol_args = Self::get_protocol_args(&protocol, url);
-            let start_str = start.to_string();
-            let duration_str = duration.to_string();
-            
-            let mut args = Vec::new();
-            
-            // 添加协议特定参数
-            args.extend(protocol_args);
-            
-            // 添加基本参数
-            args.extend(vec![
-                "-ss".to_string(), start_str,
-                "-i".to_string(), url.to_string(),
-                "-t".to_string(), duration_str,
-            ]);
-            
-            // 根据协议选择编码策略
-            match protocol {
-                StreamProtocol::RTSP | StreamProtocol::RTMP => {
-                    // 实时流视频复制，音频重新编码以确保兼容性
-                    args.extend(vec![
-                        "-c:v".to_string(), "copy".to_string(),
-                        "-c:a".to_string(), "aac".to_string(),
-                        "-b:a".to_string(), "128k".to_string(),
-                    ]);
-                },
-                StreamProtocol::HLS | StreamProtocol::HTTP => {
-                    // HTTP流可能需要重新编码以确保兼容性
-                    args.extend(vec![
-                        "-c:v".to_string(), "libx264".to_string(),
-                        "-preset".to_string(), "fast".to_string(),
-                        "-crf".to_string(), "23".to_string(),
-                    ]);
-                },
-                StreamProtocol::File => {
-                    // 本地文件视频复制，音频重新编码以确保兼容性
-                    args.extend(vec![
-                        "-c:v".to_string(), "copy".to_string(),
-                        "-c:a".to_string(), "aac".to_string(),
-                        "-b:a".to_string(), "128k".to_string(),
-                    ]);
-                },
-                StreamProtocol::Unknown => {
-                    // 未知协议使用保守的重编码
-                    args.extend(vec![
-                        "-c:v".to_string(), "libx264".to_string(),
-                        "-preset".to_string(), "fast".to_string(),
-                    ]);
-                },
+
+        // 按探测到的实际编码决定复制还是重新编码，探测失败时退回协议默认参数；
+        // 流处理器的默认编码只适用于MP4，其他容器按容器能容纳的编码选择
+        let probe = if !resolved.uses_handler_codec() || handler.probe_before_clip() {
+            match Self::run_probe(handler.as_ref(), url).await {
+                Ok(probe) => Some(probe),
+                Err(e) => {
+                    tracing::warn!("Probe before clip failed, using protocol defaults: {}", e);
+                    None
+                }
             }
-            
-            args.extend(vec![
-                "-y".to_string(),
-                output_path.clone(),
-            ]);
-            
-            args
+        } else {
+            None
+        };
+        let mut codec_args = if resolved.uses_handler_codec() {
+            handler.select_clip_codec_args(probe.as_ref())
+        } else {
+            resolved.codec_args(probe.as_ref())?
         };
+        let work_dir = tempfile::tempdir()
+            .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+        if let Some(filter) = self.video_filter(
+            &output.masks,
+            output.crop.as_ref(),
+            output.scale_filter(),
+            output.overlay.as_ref(),
+            work_dir.path(),
+        )? {
+            codec_args.extend(["-vf".to_string(), filter]);
+        }
+        let clips_dir = self.clips_dir.to_string_lossy();
+        codec_args.extend(resolved.muxer_args(package_dir.as_deref().unwrap_or(&clips_dir)));
+        let args = handler.build_clip_args(url, start, duration, codec_args, &output_path);
+
+        // 成功之前的任何退出路径（包括任务取消）都会删除残缺输出，打包输出删除整个子目录
+        let mut partial_output = PartialOutputGuard::new(package_dir.as_deref().unwrap_or(&output_path));
+        if let Some(dir) = &package_dir {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create package dir: {}", e))?;
+        }
 
-        // 使用带超时的异步执行，但只获取状态
-        let output = Self::execute_ffmpeg_with_timeout(args).await?;
+        // 使用带超时的异步执行，超时时间随剪辑时长增加
+        let limit = handler.clip_timeout_margin() + Duration::from_secs_f64(duration);
+        let output = FfmpegCommand::new(args)
+            .timeout(limit)
+            .on_progress(on_progress)
+            .run()
+            .await?;
             
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -370,49 +430,7 @@ impl VideoSnapshotService {
             }
         }
         
-            // 清理clips目录，最多只保留100个文件
-            if let Err(e) = Self::cleanup_clips_dir(10) {
-                tracing::warn!("Failed to cleanup clips dir: {}", e);
-            }
-        
-            Ok(filename)
-    }
-
-    /// 保证clips目录下最多只保留max_files个文件，删除最旧的
-    fn cleanup_clips_dir(max_files: usize) -> Result<(), String> {
-        use std::fs;
-        use std::path::Path;
-        
-        let dir = Path::new("clips");
-        let mut entries: Vec<_> = fs::read_dir(dir)
-            .map_err(|e| format!("Failed to read clips dir: {}", e))?
-            .filter_map(|e| e.ok())
-            .filter(|e| {
-                if let Some(ext) = e.path().extension() {
-                    ext == "mp4"
-                } else {
-                    false
-                }
-            })
-            .collect();
-            
-        if entries.len() > max_files {
-            // 按修改时间排序，最旧的在前
-            entries.sort_by_key(|e| e.metadata().and_then(|m| m.modified()).ok());
-            let num_to_remove = entries.len() - max_files;
-            
-            for entry in entries.iter().take(num_to_remove) {
-                if let Err(e) = fs::remove_file(entry.path()) {
-                    tracing::warn!("Failed to remove old clip: {}", e);
-                }
-            }
-        }
-        Ok(())
+        partial_output.keep();
+        Ok(filename)
     }
-
-    /// 检测流是否为实时流（保持向后兼容）
-    fn is_realtime_stream(url: &str) -> bool {
-        matches!(Self::detect_protocol(url), 
-            StreamProtocol::RTSP | StreamProtocol::RTMP | StreamProtocol::HLS)
-    }
-} 
\ No newline at end of file
+}
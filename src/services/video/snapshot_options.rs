@@ -0,0 +1,160 @@
+use super::{validate_masks, CropRegion, OverlaySpec, PrivacyMask};
+
+/// 截图输出的最大边长
+const MAX_DIMENSION: u32 = 8192;
+
+/// 截图图片格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl SnapshotFormat {
+    /// 解析请求中的格式名称
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "webp" => Ok(Self::Webp),
+            _ => Err(format!("不支持的图片格式: {}，可选 png/jpeg/webp", name)),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// 同时指定宽高时的缩放方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    /// 保持比例缩放到框内
+    #[default]
+    Contain,
+    /// 保持比例铺满后居中裁剪
+    Cover,
+    /// 拉伸到指定尺寸
+    Fill,
+}
+
+impl FitMode {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "contain" => Ok(Self::Contain),
+            "cover" => Ok(Self::Cover),
+            "fill" => Ok(Self::Fill),
+            _ => Err(format!("不支持的缩放方式: {}，可选 contain/cover/fill", name)),
+        }
+    }
+}
+
+/// 截图输出参数
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotOptions {
+    pub format: SnapshotFormat,
+    /// 图片质量 1-100，只对 jpeg/webp 生效
+    pub quality: Option<u8>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: FitMode,
+    /// 隐私遮挡，在缩放之前执行
+    pub masks: Vec<PrivacyMask>,
+    /// 遮挡之后、缩放之前裁剪的区域，配合宽高实现数字变焦
+    pub crop: Option<CropRegion>,
+    /// 缩放之后叠加的文字和水印
+    pub overlay: Option<OverlaySpec>,
+}
+
+impl SnapshotOptions {
+    /// 校验参数范围
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(quality) = self.quality
+            && !(1..=100).contains(&quality)
+        {
+            return Err("quality 必须在 1-100 之间".to_string());
+        }
+        for (name, value) in [("width", self.width), ("height", self.height)] {
+            if let Some(value) = value
+                && !(1..=MAX_DIMENSION).contains(&value)
+            {
+                return Err(format!("{} 必须在 1-{} 之间", name, MAX_DIMENSION));
+            }
+        }
+        validate_masks(&self.masks)?;
+        if let Some(crop) = &self.crop {
+            crop.validate()?;
+        }
+        if let Some(overlay) = &self.overlay {
+            overlay.validate()?;
+        }
+        Ok(())
+    }
+
+    /// 缩放滤镜，未指定尺寸时返回None
+    pub(super) fn scale_filter(&self) -> Option<String> {
+        match (self.width, self.height) {
+            (None, None) => None,
+            (Some(w), None) => Some(format!("scale={}:-1", w)),
+            (None, Some(h)) => Some(format!("scale=-1:{}", h)),
+            (Some(w), Some(h)) => Some(match self.fit {
+                FitMode::Contain => format!("scale={}:{}:force_original_aspect_ratio=decrease", w, h),
+                FitMode::Cover => format!(
+                    "scale={}:{}:force_original_aspect_ratio=increase,crop={}:{}",
+                    w, h, w, h
+                ),
+                FitMode::Fill => format!("scale={}:{}", w, h),
+            }),
+        }
+    }
+
+    /// 输出参数，放在 `-vframes 1` 之后、输出路径之前
+    ///
+    /// `video_filter` 是包含遮挡、裁剪、缩放和叠加的完整滤镜图，由服务按选项生成
+    pub fn output_args(&self, video_filter: Option<String>) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(filter) = video_filter {
+            args.extend(["-vf".to_string(), filter]);
+        }
+
+        match self.format {
+            SnapshotFormat::Png => {
+                args.extend(["-c:v".to_string(), "png".to_string()]);
+            }
+            SnapshotFormat::Jpeg => {
+                // mjpeg的 -q:v 范围是 2（最好）到 31（最差）
+                let quality = self.quality.unwrap_or(90) as u32;
+                let qscale = 31 - (quality - 1) * 29 / 99;
+                args.extend([
+                    "-c:v".to_string(), "mjpeg".to_string(),
+                    "-q:v".to_string(), qscale.to_string(),
+                ]);
+            }
+            SnapshotFormat::Webp => {
+                let quality = self.quality.unwrap_or(80);
+                args.extend([
+                    "-c:v".to_string(), "libwebp".to_string(),
+                    "-quality".to_string(), quality.to_string(),
+                ]);
+            }
+        }
+
+        args.extend(["-f".to_string(), "image2".to_string()]);
+        args
+    }
+}
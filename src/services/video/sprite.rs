@@ -0,0 +1,181 @@
+use image::{imageops, ImageEncoder, RgbImage};
+use image::codecs::jpeg::JpegEncoder;
+use serde::Serialize;
+use std::fmt::Write;
+
+use super::{CapturedFrame, PartialOutputGuard, PrivacyMask, SnapshotFormat, SnapshotOptions, VideoSnapshotService};
+
+/// 雪碧图参数
+#[derive(Debug, Clone)]
+pub struct SpriteOptions {
+    /// 缩略图数量
+    pub count: usize,
+    /// 每行缩略图数量
+    pub columns: u32,
+    /// 缩略图宽度，高度按视频比例计算
+    pub thumb_width: u32,
+    /// 雪碧图JPEG质量
+    pub quality: u8,
+    /// 视频源登记的隐私遮挡
+    pub masks: Vec<PrivacyMask>,
+}
+
+impl Default for SpriteOptions {
+    fn default() -> Self {
+        Self {
+            count: 20,
+            columns: 5,
+            thumb_width: 160,
+            quality: 80,
+            masks: Vec::new(),
+        }
+    }
+}
+
+/// 生成的雪碧图
+#[derive(Debug, Clone, Serialize)]
+pub struct SpriteSheet {
+    /// clips目录下的雪碧图文件名
+    pub sprite_file: String,
+    /// clips目录下的WebVTT文件名
+    pub vtt_file: String,
+    pub columns: u32,
+    pub rows: u32,
+    pub thumb_width: u32,
+    pub thumb_height: u32,
+    /// 实际生成的缩略图数量
+    pub frames: usize,
+}
+
+/// WebVTT时间格式 `HH:MM:SS.mmm`
+fn vtt_timestamp(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// 生成缩略图轨道，每个时间段指向雪碧图中的一格
+fn build_vtt(frames: &[CapturedFrame], duration: f64, sprite_file: &str, sheet: &SpriteSheet) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+
+    for (i, frame) in frames.iter().enumerate() {
+        let start = if i == 0 { 0.0 } else { frame.timestamp };
+        let end = frames.get(i + 1).map(|next| next.timestamp).unwrap_or(duration);
+        let x = (i as u32 % sheet.columns) * sheet.thumb_width;
+        let y = (i as u32 / sheet.columns) * sheet.thumb_height;
+
+        let _ = write!(
+            vtt,
+            "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+            vtt_timestamp(start),
+            vtt_timestamp(end),
+            sprite_file,
+            x, y, sheet.thumb_width, sheet.thumb_height
+        );
+    }
+
+    vtt
+}
+
+impl VideoSnapshotService {
+    /// 在视频中均匀取样生成缩略图雪碧图和WebVTT索引，保存到clips目录
+    ///
+    /// `duration` 为空时通过ffprobe获取，实时流必须指定。
+    /// 输出文件名为 `{stem}_sprite.jpg` 和 `{stem}_sprite.vtt`
+    pub async fn generate_sprite(
+        &self,
+        url: &str,
+        duration: Option<f64>,
+        profile: Option<&str>,
+        stem: &str,
+        options: &SpriteOptions,
+    ) -> Result<SpriteSheet, String> {
+        let duration = match duration {
+            Some(duration) => duration,
+            None => self.probe(url, profile).await?
+                .duration
+                .ok_or("无法获取视频时长，请指定 duration")?,
+        };
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err("视频时长必须大于0".to_string());
+        }
+
+        let step = duration / options.count as f64;
+        let timestamps: Vec<f64> = (0..options.count).map(|i| i as f64 * step).collect();
+        let snapshot_options = SnapshotOptions {
+            format: SnapshotFormat::Png,
+            width: Some(options.thumb_width),
+            masks: options.masks.clone(),
+            ..SnapshotOptions::default()
+        };
+        let frames = self.capture_frames(url, &timestamps, profile, &snapshot_options).await?;
+
+        let thumbs = frames
+            .iter()
+            .map(|frame| image::load_from_memory(&frame.data).map(|img| img.to_rgb8()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode thumbnail: {}", e))?;
+
+        // 以第一帧尺寸为准，个别尺寸不一致的帧缩放到同一大小
+        let thumb_width = thumbs[0].width();
+        let thumb_height = thumbs[0].height();
+        let columns = options.columns.min(thumbs.len() as u32);
+        let rows = (thumbs.len() as u32).div_ceil(columns);
+
+        let mut sheet_img = RgbImage::new(columns * thumb_width, rows * thumb_height);
+        for (i, thumb) in thumbs.iter().enumerate() {
+            let x = (i as u32 % columns) * thumb_width;
+            let y = (i as u32 / columns) * thumb_height;
+            if thumb.dimensions() == (thumb_width, thumb_height) {
+                imageops::replace(&mut sheet_img, thumb, x as i64, y as i64);
+            } else {
+                let resized = imageops::resize(thumb, thumb_width, thumb_height, imageops::FilterType::Triangle);
+                imageops::replace(&mut sheet_img, &resized, x as i64, y as i64);
+            }
+        }
+
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, options.quality)
+            .write_image(
+                sheet_img.as_raw(),
+                sheet_img.width(),
+                sheet_img.height(),
+                image::ExtendedColorType::Rgb8,
+            )
+            .map_err(|e| format!("Failed to encode sprite: {}", e))?;
+
+        let sheet = SpriteSheet {
+            sprite_file: format!("{}_sprite.jpg", stem),
+            vtt_file: format!("{}_sprite.vtt", stem),
+            columns,
+            rows,
+            thumb_width,
+            thumb_height,
+            frames: frames.len(),
+        };
+        let vtt = build_vtt(&frames, duration, &sheet.sprite_file, &sheet);
+
+        let sprite_path = self.clip_path(&sheet.sprite_file);
+        let vtt_path = self.clip_path(&sheet.vtt_file);
+        let mut sprite_guard = PartialOutputGuard::new(&sprite_path);
+        let mut vtt_guard = PartialOutputGuard::new(&vtt_path);
+        tokio::fs::write(&sprite_path, jpeg).await
+            .map_err(|e| format!("Failed to write sprite: {}", e))?;
+        tokio::fs::write(&vtt_path, vtt).await
+            .map_err(|e| format!("Failed to write vtt: {}", e))?;
+        sprite_guard.keep();
+        vtt_guard.keep();
+
+        tracing::info!(
+            "Generated sprite {} ({}x{} grid, {} frames)",
+            sheet.sprite_file, columns, rows, sheet.frames
+        );
+
+        Ok(sheet)
+    }
+}
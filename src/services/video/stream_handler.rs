@@ -1,28 +1,29 @@
-use std::process::Command;
+#![allow(clippy::upper_case_acronyms)]
+
+use std::sync::Arc;
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::ProbeResult;
+
+/// 浏览器风格的User-Agent，部分HTTP/HLS源会拒绝ffmpeg默认UA
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; VideoServer/1.0)";
+
+/// 流协议类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StreamProtocol {
-    RTSP {
-        transport: RtspTransport,
-        timeout: Duration,
-        reconnect: bool,
-    },
-    RTMP {
-        chunk_size: usize,
-        live: bool,
-    },
-    HLS {
-        segment_duration: u32,
-        playlist_size: u32,
-    },
-    HTTP {
-        timeout: Duration,
-    },
+    RTSP,
+    RTMP,
+    HLS,
+    HTTP,
+    File,
+    /// 本地录像片段列表（ffconcat）
+    Archive,
+    Unknown,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RtspTransport {
     TCP,
     UDP,
@@ -37,190 +38,247 @@ impl RtspTransport {
     }
 }
 
-pub trait StreamHandler {
-    fn build_snapshot_command(&self, url: &str, timestamp: f64, output: &str) -> Command;
-    fn build_clip_command(&self, url: &str, start: f64, duration: f64, output: &str) -> Command;
+/// 将字符串切片转换为ffmpeg参数列表
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+/// 流处理器
+///
+/// 每种协议的ffmpeg参数只在对应的处理器中定义。截图和剪辑命令默认由
+/// `input_args` 与 `clip_codec_args` 组合而成，特殊设备可以覆盖 `build_*` 方法，
+/// 或者使用摄像头配置（见 `profile.rs`）。
+pub trait StreamHandler: Send + Sync {
+    /// 处理器对应的协议
+    fn protocol(&self) -> StreamProtocol;
+
+    /// 是否能处理该URL
     fn validate_url(&self, url: &str) -> bool;
+
+    /// 输入选项，放在 `-i` 之前
+    fn input_args(&self, url: &str) -> Vec<String>;
+
+    /// 剪辑时的编码参数，探测失败时使用
+    fn clip_codec_args(&self) -> Vec<String>;
+
+    /// 剪辑前是否先用ffprobe探测流信息
+    fn probe_before_clip(&self) -> bool {
+        true
+    }
+
+    /// 根据探测结果选择剪辑编码参数
+    ///
+    /// 探测成功时按实际编码决定复制还是重新编码，否则使用 `clip_codec_args`
+    fn select_clip_codec_args(&self, probe: Option<&ProbeResult>) -> Vec<String> {
+        match probe {
+            Some(probe) => probe.mp4_codec_args(),
+            None => self.clip_codec_args(),
+        }
+    }
+
+    /// 截图的ffmpeg超时时间
+    fn snapshot_timeout(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    /// 剪辑的ffmpeg超时余量，实际超时为剪辑时长加上该余量
+    fn clip_timeout_margin(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    /// 构建截图参数
+    ///
+    /// `output_args` 是图片格式、质量和缩放参数，见 `SnapshotOptions::output_args`
+    fn build_snapshot_args(
+        &self,
+        url: &str,
+        timestamp: f64,
+        output_args: Vec<String>,
+        output: &str,
+    ) -> Vec<String> {
+        let mut args = self.input_args(url);
+        args.extend(vec![
+            "-i".to_string(), url.to_string(),
+            "-ss".to_string(), timestamp.to_string(),
+            "-vframes".to_string(), "1".to_string(),
+        ]);
+        args.extend(output_args);
+        args.extend(vec![
+            "-y".to_string(),
+            output.to_string(),
+        ]);
+        args
+    }
+
+    /// 构建多帧截图参数
+    ///
+    /// 只打开一次输入，每个时间戳对应一个输出，各输出分别用 `-ss` 定位
+    fn build_batch_snapshot_args(
+        &self,
+        url: &str,
+        frames: &[(f64, String)],
+        output_args: &[String],
+    ) -> Vec<String> {
+        let mut args = self.input_args(url);
+        args.extend(vec![
+            "-y".to_string(),
+            "-i".to_string(), url.to_string(),
+        ]);
+        for (timestamp, output) in frames {
+            args.extend(vec![
+                "-ss".to_string(), timestamp.to_string(),
+                "-vframes".to_string(), "1".to_string(),
+            ]);
+            args.extend(output_args.iter().cloned());
+            args.push(output.clone());
+        }
+        args
+    }
+
+    /// 构建剪辑参数
+    fn build_clip_args(
+        &self,
+        url: &str,
+        start: f64,
+        duration: f64,
+        codec_args: Vec<String>,
+        output: &str,
+    ) -> Vec<String> {
+        let mut args = self.input_args(url);
+        args.extend(vec![
+            "-ss".to_string(), start.to_string(),
+            "-i".to_string(), url.to_string(),
+            "-t".to_string(), duration.to_string(),
+        ]);
+        args.extend(codec_args);
+        args.extend(vec![
+            "-y".to_string(),
+            output.to_string(),
+        ]);
+        args
+    }
 }
 
 pub struct RTSPHandler {
     transport: RtspTransport,
     timeout: Duration,
-    reconnect: bool,
 }
 
 impl RTSPHandler {
-    pub fn new(transport: RtspTransport, timeout: Duration, reconnect: bool) -> Self {
+    pub fn new(transport: RtspTransport, timeout: Duration) -> Self {
         Self {
             transport,
             timeout,
-            reconnect,
         }
     }
 }
 
 impl StreamHandler for RTSPHandler {
-    fn build_snapshot_command(&self, url: &str, timestamp: f64, output: &str) -> Command {
-        let mut cmd = Command::new("ffmpeg");
-        cmd.args([
-            "-rtsp_transport", self.transport.as_str(),
-            "-timeout", &self.timeout.as_micros().to_string(),
-            "-analyzeduration", "5000000",
-            "-probesize", "5000000",
-            "-i", url,
-            "-ss", &timestamp.to_string(),
-            "-vframes", "1",
-            "-f", "image2",
-            "-y",
-            output,
-        ]);
+    fn protocol(&self) -> StreamProtocol {
+        StreamProtocol::RTSP
+    }
 
-        cmd
+    fn validate_url(&self, url: &str) -> bool {
+        url.starts_with("rtsp://")
     }
 
-    fn build_clip_command(&self, url: &str, start: f64, duration: f64, output: &str) -> Command {
-        let mut cmd = Command::new("ffmpeg");
-        cmd.args([
+    fn input_args(&self, _url: &str) -> Vec<String> {
+        // Docker环境优化的RTSP参数
+        to_args(&[
             "-rtsp_transport", self.transport.as_str(),
             "-timeout", &self.timeout.as_micros().to_string(),
+            "-fflags", "nobuffer",
+            "-flags", "low_delay",
+            "-strict", "experimental",
             "-analyzeduration", "5000000",
             "-probesize", "5000000",
-            "-ss", &start.to_string(),
-            "-i", url,
-            "-t", &duration.to_string(),
-            "-c:v", "copy", // 视频流复制
-            "-c:a", "aac",  // 音频重新编码为AAC
-            "-b:a", "128k", // 音频比特率
-            "-y",
-            output,
-        ]);
-
-        cmd
+            "-max_delay", "500000",
+            "-thread_queue_size", "512",
+        ])
     }
 
-    fn validate_url(&self, url: &str) -> bool {
-        url.starts_with("rtsp://")
+    fn clip_codec_args(&self) -> Vec<String> {
+        // 实时流视频复制，音频重新编码以确保兼容性
+        to_args(&[
+            "-c:v", "copy",
+            "-c:a", "aac",
+            "-b:a", "128k",
+        ])
     }
 }
 
 pub struct RTMPHandler {
-    chunk_size: usize,
-    live: bool,
+    timeout: Duration,
 }
 
 impl RTMPHandler {
-    pub fn new(chunk_size: usize, live: bool) -> Self {
-        Self {
-            chunk_size,
-            live,
-        }
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
     }
 }
 
 impl StreamHandler for RTMPHandler {
-    fn build_snapshot_command(&self, url: &str, timestamp: f64, output: &str) -> Command {
-        let mut cmd = Command::new("ffmpeg");
-        cmd.args([
-            "-i", url,
-            "-ss", &timestamp.to_string(),
-            "-vframes", "1",
-            "-f", "image2",
-            "-y",
-        ]);
-
-        if self.live {
-            cmd.args([
-                "-live_start_index", "0",
-                "-analyzeduration", "1000000", // 1秒
-                "-probesize", "1000000",
-            ]);
-        }
-
-        cmd.arg(output);
-        cmd
+    fn protocol(&self) -> StreamProtocol {
+        StreamProtocol::RTMP
     }
 
-    fn build_clip_command(&self, url: &str, start: f64, duration: f64, output: &str) -> Command {
-        let mut cmd = Command::new("ffmpeg");
-        
-        if self.live {
-            cmd.args([
-                "-live_start_index", "0",
-                "-analyzeduration", "1000000",
-                "-probesize", "1000000",
-            ]);
-        }
-
-        cmd.args([
-            "-i", url,
-            "-ss", &start.to_string(),
-            "-t", &duration.to_string(),
-            "-c:v", "libx264", // RTMP流通常需要重新编码
-            "-preset", "veryfast",
-            "-maxrate", "3000k",
-            "-bufsize", "6000k",
-            "-y",
-            output,
-        ]);
+    fn validate_url(&self, url: &str) -> bool {
+        url.starts_with("rtmp://")
+    }
 
-        cmd
+    fn input_args(&self, _url: &str) -> Vec<String> {
+        to_args(&[
+            "-timeout", &self.timeout.as_micros().to_string(),
+            "-analyzeduration", "2000000",
+            "-probesize", "2000000",
+        ])
     }
 
-    fn validate_url(&self, url: &str) -> bool {
-        url.starts_with("rtmp://")
+    fn clip_codec_args(&self) -> Vec<String> {
+        // 实时流视频复制，音频重新编码以确保兼容性
+        to_args(&[
+            "-c:v", "copy",
+            "-c:a", "aac",
+            "-b:a", "128k",
+        ])
     }
 }
 
 pub struct HLSHandler {
-    segment_duration: u32,
-    playlist_size: u32,
+    timeout: Duration,
 }
 
 impl HLSHandler {
-    pub fn new(segment_duration: u32, playlist_size: u32) -> Self {
-        Self {
-            segment_duration,
-            playlist_size,
-        }
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
     }
 }
 
 impl StreamHandler for HLSHandler {
-    fn build_snapshot_command(&self, url: &str, timestamp: f64, output: &str) -> Command {
-        let mut cmd = Command::new("ffmpeg");
-        cmd.args([
-            "-i", url,
-            "-ss", &timestamp.to_string(),
-            "-vframes", "1",
-            "-f", "image2",
-            "-y",
-            "-timeout", "10000000",
-            output,
-        ]);
-
-        cmd
+    fn protocol(&self) -> StreamProtocol {
+        StreamProtocol::HLS
     }
 
-    fn build_clip_command(&self, url: &str, start: f64, duration: f64, output: &str) -> Command {
-        let mut cmd = Command::new("ffmpeg");
-        cmd.args([
-            "-i", url,
-            "-ss", &start.to_string(),
-            "-t", &duration.to_string(),
-            "-c:v", "libx264",
-            "-hls_time", &self.segment_duration.to_string(),
-            "-hls_list_size", &self.playlist_size.to_string(),
-            "-hls_flags", "delete_segments",
-            "-f", "hls",
-            "-y",
-            output,
-        ]);
+    fn validate_url(&self, url: &str) -> bool {
+        url.contains(".m3u8") || url.starts_with("hls://")
+    }
 
-        cmd
+    fn input_args(&self, _url: &str) -> Vec<String> {
+        to_args(&[
+            "-timeout", &self.timeout.as_micros().to_string(),
+            "-user_agent", USER_AGENT,
+            "-analyzeduration", "3000000",
+            "-probesize", "3000000",
+        ])
     }
 
-    fn validate_url(&self, url: &str) -> bool {
-        url.ends_with(".m3u8") || url.starts_with("http") && url.contains("m3u8")
+    fn clip_codec_args(&self) -> Vec<String> {
+        // HTTP流可能需要重新编码以确保兼容性
+        to_args(&[
+            "-c:v", "libx264",
+            "-preset", "fast",
+            "-crf", "23",
+        ])
     }
 }
 
@@ -235,53 +293,165 @@ impl HTTPHandler {
 }
 
 impl StreamHandler for HTTPHandler {
-    fn build_snapshot_command(&self, url: &str, timestamp: f64, output: &str) -> Command {
-        let mut cmd = Command::new("ffmpeg");
-        cmd.args([
-            "-i", url,
-            "-ss", &timestamp.to_string(),
-            "-vframes", "1",
-            "-f", "image2",
-            "-y",
+    fn protocol(&self) -> StreamProtocol {
+        StreamProtocol::HTTP
+    }
+
+    fn validate_url(&self, url: &str) -> bool {
+        url.starts_with("http://") || url.starts_with("https://")
+    }
+
+    fn input_args(&self, _url: &str) -> Vec<String> {
+        to_args(&[
+            "-user_agent", USER_AGENT,
             "-timeout", &self.timeout.as_micros().to_string(),
-            output,
-        ]);
+        ])
+    }
 
-        cmd
+    fn clip_codec_args(&self) -> Vec<String> {
+        to_args(&[
+            "-c:v", "libx264",
+            "-preset", "fast",
+            "-crf", "23",
+        ])
+    }
+}
+
+/// 本地文件
+pub struct FileHandler;
+
+impl StreamHandler for FileHandler {
+    fn protocol(&self) -> StreamProtocol {
+        StreamProtocol::File
+    }
+
+    fn validate_url(&self, url: &str) -> bool {
+        url.starts_with("file://") || (!url.contains("://") && std::path::Path::new(url).exists())
+    }
+
+    fn input_args(&self, _url: &str) -> Vec<String> {
+        Vec::new()
     }
 
-    fn build_clip_command(&self, url: &str, start: f64, duration: f64, output: &str) -> Command {
-        let mut cmd = Command::new("ffmpeg");
-        cmd.args([
-            "-i", url,
-            "-ss", &start.to_string(),
-            "-t", &duration.to_string(),
+    fn clip_codec_args(&self) -> Vec<String> {
+        // 本地文件视频复制，音频重新编码以确保兼容性
+        to_args(&[
             "-c:v", "copy",
-            "-y",
-            "-timeout", &self.timeout.as_micros().to_string(),
-            output,
-        ]);
+            "-c:a", "aac",
+            "-b:a", "128k",
+        ])
+    }
+}
 
-        cmd
+/// 录像片段列表处理器
+///
+/// 输入是服务生成的ffconcat列表，列出同一视频源连续的录像片段，用concat demuxer读取。
+/// concat demuxer会打开列表中的任意路径，所以不注册到注册表，只由 `clip_archive` 直接使用
+pub struct ArchiveHandler;
+
+impl StreamHandler for ArchiveHandler {
+    fn protocol(&self) -> StreamProtocol {
+        StreamProtocol::Archive
     }
 
     fn validate_url(&self, url: &str) -> bool {
-        url.starts_with("http://") || url.starts_with("https://")
+        !url.contains("://") && url.ends_with(".ffconcat")
+    }
+
+    fn input_args(&self, _url: &str) -> Vec<String> {
+        // 列表中是录像目录的绝对路径
+        to_args(&["-f", "concat", "-safe", "0"])
+    }
+
+    fn clip_codec_args(&self) -> Vec<String> {
+        // 录像本身是摄像头原始编码，直接复制
+        to_args(&["-c", "copy"])
+    }
+}
+
+/// 未识别协议的兜底处理器
+pub struct GenericHandler {
+    timeout: Duration,
+}
+
+impl GenericHandler {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl StreamHandler for GenericHandler {
+    fn protocol(&self) -> StreamProtocol {
+        StreamProtocol::Unknown
+    }
+
+    fn validate_url(&self, _url: &str) -> bool {
+        true
+    }
+
+    fn input_args(&self, _url: &str) -> Vec<String> {
+        to_args(&[
+            "-timeout", &self.timeout.as_micros().to_string(),
+            "-analyzeduration", "3000000",
+            "-probesize", "3000000",
+        ])
+    }
+
+    fn clip_codec_args(&self) -> Vec<String> {
+        // 未知协议使用保守的重编码
+        to_args(&[
+            "-c:v", "libx264",
+            "-preset", "fast",
+        ])
     }
 }
 
-pub fn create_handler(url: &str) -> Box<dyn StreamHandler> {
-    if url.starts_with("rtsp://") {
-        Box::new(RTSPHandler::new(
-            RtspTransport::TCP,
-            Duration::from_secs(10),
-            true,
-        ))
-    } else if url.starts_with("rtmp://") {
-        Box::new(RTMPHandler::new(4096, true))
-    } else if url.ends_with(".m3u8") || (url.starts_with("http") && url.contains("m3u8")) {
-        Box::new(HLSHandler::new(6, 10))
-    } else {
-        Box::new(HTTPHandler::new(Duration::from_secs(30)))
-    }
-}
\ No newline at end of file
+/// 流处理器注册表
+///
+/// 按注册的逆序匹配URL，后注册的处理器优先，因此自定义处理器可以覆盖内置处理器。
+/// 没有处理器匹配时使用兜底处理器。
+#[derive(Clone)]
+pub struct StreamHandlerRegistry {
+    handlers: Vec<Arc<dyn StreamHandler>>,
+    fallback: Arc<dyn StreamHandler>,
+}
+
+impl StreamHandlerRegistry {
+    /// 创建空注册表，只包含兜底处理器
+    pub fn empty() -> Self {
+        Self {
+            handlers: Vec::new(),
+            fallback: Arc::new(GenericHandler::new(Duration::from_secs(10))),
+        }
+    }
+
+    /// 注册处理器
+    pub fn register(mut self, handler: impl StreamHandler + 'static) -> Self {
+        self.handlers.push(Arc::new(handler));
+        self
+    }
+
+    /// 查找能处理该URL的处理器
+    pub fn resolve(&self, url: &str) -> Arc<dyn StreamHandler> {
+        self.handlers
+            .iter()
+            .rev()
+            .find(|handler| handler.validate_url(url))
+            .cloned()
+            .unwrap_or_else(|| self.fallback.clone())
+    }
+}
+
+impl Default for StreamHandlerRegistry {
+    /// 内置协议处理器
+    fn default() -> Self {
+        let timeout = Duration::from_secs(10);
+
+        Self::empty()
+            .register(FileHandler)
+            .register(HTTPHandler::new(timeout))
+            .register(HLSHandler::new(timeout))
+            .register(RTMPHandler::new(timeout))
+            .register(RTSPHandler::new(RtspTransport::TCP, timeout))
+    }
+}
@@ -0,0 +1,108 @@
+use axum::http::StatusCode;
+use serde::Serialize;
+
+/// 失败原因分类，根据ffmpeg和服务返回的错误信息判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// 拉流或处理超时
+    Timeout,
+    /// 无法连接摄像头或服务器
+    Connection,
+    /// 认证失败或没有权限
+    Auth,
+    /// 流地址或文件不存在
+    NotFound,
+    /// 流能打开但没有可用画面
+    InvalidStream,
+    /// 其他错误
+    Internal,
+}
+
+impl ErrorCategory {
+    /// 按错误信息中的关键字归类，ffmpeg的真正错误通常在输出末尾，所以从后往前匹配
+    pub fn classify(message: &str) -> Self {
+        // 拉流地址中可能带有 timeout 参数，连接错误优先于超时
+        const RULES: [(ErrorCategory, &[&str]); 5] = [
+            (ErrorCategory::Auth, &["unauthorized", "forbidden", "authorization failed"]),
+            (ErrorCategory::NotFound, &["not found", "no such file"]),
+            (
+                ErrorCategory::Connection,
+                &[
+                    "connection refused", "connection reset", "no route to host", "network is unreachable",
+                    "name or service not known", "failed to resolve", "could not resolve", "broken pipe",
+                ],
+            ),
+            (ErrorCategory::Timeout, &["timeout", "timed out"]),
+            (
+                ErrorCategory::InvalidStream,
+                &[
+                    "invalid data", "does not contain any stream", "could not find codec", "error while decoding",
+                    "output file is empty", "was not created", "is empty", "no frames",
+                ],
+            ),
+        ];
+
+        for line in message.to_ascii_lowercase().lines().rev() {
+            for (category, keywords) in RULES {
+                if keywords.iter().any(|keyword| line.contains(keyword)) {
+                    return category;
+                }
+            }
+        }
+        ErrorCategory::Internal
+    }
+
+    /// 返回JSON错误时使用的状态码，摄像头侧的问题按网关错误处理
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::Connection | Self::Auth | Self::NotFound | Self::InvalidStream => StatusCode::BAD_GATEWAY,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// 与JSON中 `category` 相同的名称，用于响应头
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Connection => "connection",
+            Self::Auth => "auth",
+            Self::NotFound => "not_found",
+            Self::InvalidStream => "invalid_stream",
+            Self::Internal => "internal",
+        }
+    }
+
+    /// 错误图片上显示的名称
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Timeout => "TIMEOUT",
+            Self::Connection => "CONNECTION FAILED",
+            Self::Auth => "AUTH FAILED",
+            Self::NotFound => "NOT FOUND",
+            Self::InvalidStream => "INVALID STREAM",
+            Self::Internal => "INTERNAL ERROR",
+        }
+    }
+}
+
+/// 截图失败时的返回方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// 返回渲染了错误信息的PNG图片，状态码200，便于直接显示在监控墙上
+    #[default]
+    Image,
+    /// 返回JSON错误信息和对应的状态码
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "image" => Ok(Self::Image),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("不支持的错误返回方式: {}，可选 image/json", name)),
+        }
+    }
+}
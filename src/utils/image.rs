@@ -1,28 +1,90 @@
 use image::codecs::png::PngEncoder;
-use image::ImageEncoder;
+use image::{ImageEncoder, Rgb, RgbImage};
 
-pub fn create_error_image(_error_msg: &str) -> Vec<u8> {
-    // 创建一个错误提示图片
-    let imgx = 400;
-    let imgy = 200;
-    let mut img = image::RgbImage::new(imgx, imgy);
-    
-    // 填充红色背景
-    for pixel in img.pixels_mut() {
-        *pixel = image::Rgb([255, 200, 200]);
+use crate::services::redact_text;
+use super::{char_advance, draw_text, format_utc, line_height, now_secs, ErrorCategory};
+
+const IMAGE_WIDTH: u32 = 640;
+const IMAGE_HEIGHT: u32 = 360;
+/// 文字离边缘的距离（像素）
+const PADDING: u32 = 16;
+/// 标题栏高度（像素）
+const HEADER_HEIGHT: u32 = 48;
+/// 正文字体放大倍数
+const TEXT_SCALE: u32 = 2;
+/// 正文行间距（像素）
+const LINE_GAP: u32 = 4;
+
+/// 生成截图失败的提示图片
+///
+/// 画面上依次显示错误分类、视频源名称、UTC时间和错误信息，错误信息过长时保留末尾部分，
+/// ffmpeg的真正错误通常在输出末尾。地址中的凭据会被去掉。使用内置点阵字体，非ASCII字符显示为 `?`
+pub fn create_error_image(error_msg: &str, source: &str, category: ErrorCategory) -> Vec<u8> {
+    let mut img = RgbImage::from_pixel(IMAGE_WIDTH, IMAGE_HEIGHT, Rgb([40, 40, 48]));
+
+    // 标题栏
+    for y in 0..HEADER_HEIGHT {
+        for x in 0..IMAGE_WIDTH {
+            img.put_pixel(x, y, Rgb([180, 30, 40]));
+        }
     }
-    
+    let title_scale = 3;
+    let title_y = (HEADER_HEIGHT - line_height(title_scale)) / 2 + title_scale;
+    draw_text(&mut img, PADDING, title_y, category.title(), title_scale, Rgb([255, 255, 255]));
+
+    let columns = ((IMAGE_WIDTH - PADDING * 2) / char_advance(TEXT_SCALE)) as usize;
+    let row_height = line_height(TEXT_SCALE) + LINE_GAP;
+    let mut y = HEADER_HEIGHT + PADDING;
+    let info = [
+        format!("SOURCE: {}", source),
+        format!("TIME:   {} UTC", format_utc(now_secs())),
+    ];
+    for line in &info {
+        draw_text(&mut img, PADDING, y, &truncate(line, columns), TEXT_SCALE, Rgb([255, 210, 120]));
+        y += row_height;
+    }
+    y += row_height / 2;
+
+    // 错误信息按宽度折行，放不下时保留最后几行
+    let rows = ((IMAGE_HEIGHT - PADDING - y) / row_height) as usize;
+    let lines: Vec<String> = error_msg
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .flat_map(|line| wrap(&redact_text(line), columns))
+        .collect();
+    for line in &lines[lines.len().saturating_sub(rows)..] {
+        draw_text(&mut img, PADDING, y, line, TEXT_SCALE, Rgb([230, 230, 230]));
+        y += row_height;
+    }
+
     // 编码为 PNG
     let mut buf = Vec::new();
     {
         let encoder = PngEncoder::new(&mut buf);
         encoder.write_image(
             img.as_raw(),
-            imgx,
-            imgy,
+            IMAGE_WIDTH,
+            IMAGE_HEIGHT,
             image::ExtendedColorType::Rgb8,
         ).unwrap();
     }
     
     buf
-} 
\ No newline at end of file
+}
+
+/// 超出宽度的部分用 `...` 代替
+fn truncate(text: &str, columns: usize) -> String {
+    if text.chars().count() <= columns {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(columns.saturating_sub(3)).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+/// 按字符数折行
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars.chunks(columns.max(1)).map(|chunk| chunk.iter().collect()).collect()
+}
@@ -1,5 +1,11 @@
+pub mod error;
+pub mod font;
 pub mod image;
 pub mod system;
+pub mod time;
  
+pub use error::*;
+pub use font::*;
 pub use image::*;
-pub use system::*; 
\ No newline at end of file
+pub use system::*;
+pub use time::*; 
\ No newline at end of file
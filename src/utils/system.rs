@@ -1,6 +1,6 @@
 use std::sync::atomic::Ordering;
 use crate::models::AppState;
-use crate::services::notification::send_feishu_webhook;
+use crate::services::send_feishu_webhook;
 
 pub async fn send_feishu_notification(
     state: &AppState,
@@ -17,4 +17,13 @@ pub async fn send_feishu_notification(
             let _ = send_feishu_webhook(&webhook_url_clone, &msg_clone).await;
         });
     }
-} 
\ No newline at end of file
+} 
+/// 对外访问地址
+///
+/// 配置了 `PUBLIC_BASE_URL` 时拼成完整URL，便于通知消息中直接点击，否则返回原路径
+pub fn public_url(path: &str) -> String {
+    match std::env::var("PUBLIC_BASE_URL") {
+        Ok(base) if !base.is_empty() => format!("{}{}", base.trim_end_matches('/'), path),
+        _ => path.to_string(),
+    }
+}
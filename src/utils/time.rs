@@ -0,0 +1,180 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 当前Unix时间戳（秒）
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 当前Unix时间戳（秒，带小数）
+pub fn now_secs_f64() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// 把Unix时间戳格式化为UTC时间 `YYYY-MM-DD HH:MM:SS`
+pub fn format_utc(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    // 按公历从1970-01-01推算年月日
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year, month, day, rem / 3600, rem % 3600 / 60, rem % 60
+    )
+}
+
+/// 把UTC年月日时分秒转换为Unix时间戳（秒），`format_utc` 的逆运算
+pub fn utc_to_unix(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    days * 86400 + (hour * 3600 + minute * 60 + second) as i64
+}
+
+/// 解析时间：Unix秒，或带时区的RFC 3339时间，例如 `2026-10-17T14:03:00Z`、`2026-10-17 22:03:00.5+08:00`
+///
+/// RFC 3339时间必须带时区（`Z` 或 `±HH:MM`），不按服务器时区猜测
+pub fn parse_timestamp(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+    let invalid = || format!("无效的时间: {}，应为Unix秒或带时区的RFC 3339时间（如 2026-10-17T14:03:00Z）", value);
+    if let Ok(secs) = value.parse::<f64>() {
+        return if secs.is_finite() && secs > 0.0 { Ok(secs) } else { Err(invalid()) };
+    }
+    // 只接受数字，u32::parse 会接受前导的 `+`
+    let number = |s: &str| -> Result<u32, String> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        s.parse().map_err(|_| invalid())
+    };
+
+    if !value.is_ascii() || value.len() < 20 {
+        return Err(invalid());
+    }
+    let (date, rest) = value.split_at(10);
+    let (separator, rest) = rest.split_at(1);
+    if !matches!(separator, "T" | "t" | " ") || &date[4..5] != "-" || &date[7..8] != "-" {
+        return Err(invalid());
+    }
+
+    let (time, offset) = match rest.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let index = rest.rfind(['+', '-']).ok_or_else(invalid)?;
+            let (time, zone) = rest.split_at(index);
+            let (hours, minutes) = zone[1..].split_once(':').ok_or_else(invalid)?;
+            let (hours, minutes) = (number(hours)?, number(minutes)?);
+            if hours > 23 || minutes > 59 {
+                return Err(invalid());
+            }
+            let offset = (hours * 3600 + minutes * 60) as i64;
+            (time, if zone.starts_with('-') { -offset } else { offset })
+        }
+    };
+    if time.len() < 8 || &time[2..3] != ":" || &time[5..6] != ":" {
+        return Err(invalid());
+    }
+    let (whole, fraction) = time[6..].split_at(2);
+    let fraction = match fraction.strip_prefix('.') {
+        // 小数位数不限，不能按u32解析
+        Some(digits) if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("0.{}", digits).parse::<f64>().map_err(|_| invalid())?
+        }
+        None if fraction.is_empty() => 0.0,
+        _ => return Err(invalid()),
+    };
+
+    let (year, month, day) = (number(&date[0..4])?, number(&date[5..7])?, number(&date[8..10])?);
+    let (hour, minute, second) = (number(&time[0..2])?, number(&time[3..5])?, number(whole)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+    let secs = utc_to_unix(year as i64, month, day, hour, minute, second) - offset;
+    Ok(secs as f64 + fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-10-17T14:03:00Z
+    const BASE: f64 = 1_792_245_780.0;
+
+    #[test]
+    fn unix_seconds() {
+        assert_eq!(parse_timestamp("1792245780"), Ok(BASE));
+        assert_eq!(parse_timestamp(" 1792245780.25 "), Ok(BASE + 0.25));
+        assert!(parse_timestamp("0").is_err());
+        assert!(parse_timestamp("-5").is_err());
+        assert!(parse_timestamp("inf").is_err());
+        assert!(parse_timestamp("NaN").is_err());
+    }
+
+    #[test]
+    fn rfc3339_offsets() {
+        assert_eq!(parse_timestamp("2026-10-17T14:03:00Z"), Ok(BASE));
+        assert_eq!(parse_timestamp("2026-10-17t14:03:00z"), Ok(BASE));
+        assert_eq!(parse_timestamp("2026-10-17 22:03:00+08:00"), Ok(BASE));
+        assert_eq!(parse_timestamp("2026-10-17T09:33:00-04:30"), Ok(BASE));
+        assert_eq!(parse_timestamp("2026-10-17T14:03:00+00:00"), Ok(BASE));
+        assert_eq!(parse_timestamp("2026-10-17T14:03:00-00:00"), Ok(BASE));
+        // 跨日
+        assert_eq!(parse_timestamp("2026-10-18T01:03:00+11:00"), Ok(BASE));
+    }
+
+    #[test]
+    fn rfc3339_fractional_seconds() {
+        assert_eq!(parse_timestamp("2026-10-17T14:03:00.5Z"), Ok(BASE + 0.5));
+        assert_eq!(parse_timestamp("2026-10-17T22:03:00.250+08:00"), Ok(BASE + 0.25));
+        assert_eq!(parse_timestamp("2026-10-17T14:03:00.1234567890123Z"), Ok(BASE + 0.1234567890123));
+        assert!(parse_timestamp("2026-10-17T14:03:00.Z").is_err());
+        assert!(parse_timestamp("2026-10-17T14:03:00.+1Z").is_err());
+        assert!(parse_timestamp("2026-10-17T14:03:00,5Z").is_err());
+    }
+
+    #[test]
+    fn invalid_input() {
+        for value in [
+            "",
+            "now",
+            // 没有时区
+            "2026-10-17T14:03:00",
+            "2026-10-17T14:03:00.5",
+            "2026-10-17",
+            "2026/10/17T14:03:00Z",
+            "2026-10-17X14:03:00Z",
+            "2026-13-17T14:03:00Z",
+            "2026-10-32T14:03:00Z",
+            "2026-10-00T14:03:00Z",
+            "2026-10-17T24:03:00Z",
+            "2026-10-17T14:60:00Z",
+            "2026-10-17T14:03:60Z",
+            "2026-10-17T14-03-00Z",
+            "2026-10-17T14:03:00+24:00",
+            "2026-10-17T14:03:00+08:60",
+            "2026-10-17T14:03:00+0800",
+            "2026-10-17T14:03:00++8:00",
+            "2026-1a-17T14:03:00Z",
+            "2026-10-17T14:03:0０Z",
+        ] {
+            assert!(parse_timestamp(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+}